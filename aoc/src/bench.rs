@@ -0,0 +1,235 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Result, anyhow};
+use clap::Args;
+
+use cli::MemoryProfile;
+
+use crate::{
+    days::{DAY_COUNT, InputKind},
+    runner,
+};
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Days to benchmark, e.g. "1,5,7". Benchmarks every day if omitted.
+    #[arg(short, long, value_delimiter = ',')]
+    days: Vec<u8>,
+
+    /// Parts to benchmark.
+    #[arg(short, long, value_delimiter = ',', default_values_t = [1, 2])]
+    parts: Vec<u8>,
+
+    /// Number of runs per part.
+    #[arg(short, long, default_value_t = 5)]
+    runs: u32,
+
+    /// Benchmarks against the example input instead of the real input.
+    #[arg(long)]
+    example: bool,
+
+    /// Also reports allocations and peak heap usage per part.
+    #[arg(long)]
+    profile_mem: bool,
+
+    /// Appends the results as CSV to a file, to track them over time.
+    #[arg(long)]
+    append: Option<PathBuf>,
+}
+
+/// Timings and memory usage of one part over several runs.
+struct Measurement {
+    day: u8,
+    part: u8,
+    kind: InputKind,
+    result: Result<Stats, String>,
+}
+
+struct Stats {
+    min: Duration,
+    mean: Duration,
+    max: Duration,
+    memory: Option<MemoryProfile>,
+}
+
+/// Runs the requested parts repeatedly and prints their timings.
+pub fn run(args: BenchArgs) -> Result<()> {
+    if args.runs == 0 {
+        return Err(anyhow!("Invalid number of runs: {}", args.runs));
+    }
+
+    let days = if args.days.is_empty() {
+        (1..=DAY_COUNT).collect()
+    } else {
+        args.days.clone()
+    };
+    let kind = if args.example {
+        InputKind::Example
+    } else {
+        InputKind::Real
+    };
+
+    // Every solver installs the counting allocator, which only counts with --profile-mem.
+    if args.profile_mem {
+        println!("Timings include counting allocations.");
+    } else {
+        println!("Timings include the counting allocator, which is idle without --profile-mem.");
+    }
+    println!(
+        "{:>3} {:>4} {:>10} {:>10} {:>10} {:>12} {:>12} {:>12}",
+        "day", "part", "min", "mean", "max", "allocations", "allocated", "peak"
+    );
+
+    let mut measurements = Vec::new();
+    for day in days {
+        for part in &args.parts {
+            let measurement = Measurement {
+                day,
+                part: *part,
+                kind,
+                result: measure(day, *part, kind, args.runs, args.profile_mem),
+            };
+            println!("{}", format_row(&measurement));
+
+            measurements.push(measurement);
+        }
+    }
+
+    if let Some(path) = args.append {
+        append_csv(&path, &measurements)?;
+    }
+
+    Ok(())
+}
+
+fn measure(
+    day: u8,
+    part: u8,
+    kind: InputKind,
+    runs: u32,
+    profile_mem: bool,
+) -> Result<Stats, String> {
+    let mut elapsed = Vec::new();
+    let mut memory = None;
+
+    for _ in 0..runs {
+        let run = runner::run(day, part, kind, profile_mem).map_err(|error| error.to_string())?;
+        run.answer?;
+
+        elapsed.push(run.elapsed);
+        memory = run.memory;
+    }
+
+    Ok(Stats {
+        min: elapsed.iter().min().copied().unwrap_or_default(),
+        mean: elapsed.iter().sum::<Duration>() / runs,
+        max: elapsed.iter().max().copied().unwrap_or_default(),
+        memory,
+    })
+}
+
+fn format_row(measurement: &Measurement) -> String {
+    let Measurement { day, part, .. } = measurement;
+
+    match &measurement.result {
+        Ok(stats) => {
+            let (allocations, allocated, peak) = match &stats.memory {
+                Some(memory) => (
+                    memory.allocations.to_string(),
+                    format_bytes(memory.allocated_bytes),
+                    format_bytes(memory.peak_heap_bytes),
+                ),
+                None => (String::from("-"), String::from("-"), String::from("-")),
+            };
+
+            format!(
+                "{day:>3} {part:>4} {:>10} {:>10} {:>10} {allocations:>12} {allocated:>12} {peak:>12}",
+                format!("{:.2?}", stats.min),
+                format!("{:.2?}", stats.mean),
+                format!("{:.2?}", stats.max),
+            )
+        }
+        Err(error) => format!("{day:>3} {part:>4} error: {error}"),
+    }
+}
+
+/// Formats a byte count with a binary unit, e.g. "1.50 MiB".
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.2} {}", UNITS[unit])
+    }
+}
+
+/// Appends successful measurements to a CSV file, writing the header if the file is new.
+fn append_csv(path: &Path, measurements: &[Measurement]) -> Result<()> {
+    let is_new = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    if is_new {
+        writeln!(
+            file,
+            "timestamp,day,part,input,min_ns,mean_ns,max_ns,allocations,allocated_bytes,peak_heap_bytes"
+        )?;
+    }
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    for measurement in measurements {
+        let Ok(stats) = &measurement.result else {
+            continue;
+        };
+
+        let (allocations, allocated, peak) = match &stats.memory {
+            Some(memory) => (
+                memory.allocations.to_string(),
+                memory.allocated_bytes.to_string(),
+                memory.peak_heap_bytes.to_string(),
+            ),
+            None => (String::new(), String::new(), String::new()),
+        };
+
+        writeln!(
+            file,
+            "{timestamp},{},{},{},{},{},{},{allocations},{allocated},{peak}",
+            measurement.day,
+            measurement.part,
+            measurement.kind,
+            stats.min.as_nanos(),
+            stats.mean.as_nanos(),
+            stats.max.as_nanos(),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_small_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+    }
+
+    #[test]
+    fn format_large_bytes() {
+        assert_eq!(format_bytes(1536), "1.50 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.00 MiB");
+    }
+}
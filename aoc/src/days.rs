@@ -0,0 +1,102 @@
+use std::{fmt, fs, path::PathBuf};
+
+use anyhow::{Result, anyhow};
+
+use cli::trim_newlines;
+
+/// The workspace root, where the day crates and the inputs directory live.
+pub const WORKSPACE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
+
+pub const DAY_COUNT: u8 = 25;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InputKind {
+    Example,
+    Real,
+}
+
+impl InputKind {
+    pub fn toggle(self) -> Self {
+        match self {
+            InputKind::Example => InputKind::Real,
+            InputKind::Real => InputKind::Example,
+        }
+    }
+}
+
+impl fmt::Display for InputKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputKind::Example => write!(f, "example"),
+            InputKind::Real => write!(f, "real"),
+        }
+    }
+}
+
+/// Gets the path to the puzzle input of a day.
+pub fn input_path(day: u8) -> PathBuf {
+    PathBuf::from(WORKSPACE_DIR).join(format!("inputs/day-{day}.txt"))
+}
+
+/// Gets the path to the solver source of a day.
+pub fn source_path(day: u8) -> PathBuf {
+    PathBuf::from(WORKSPACE_DIR).join(format!("day-{day}/src/main.rs"))
+}
+
+/// Loads the input of a day, trimmed the same way as [cli::get_part].
+pub fn load_input(day: u8, kind: InputKind) -> Result<String> {
+    let input = match kind {
+        InputKind::Example => extract_example(&fs::read_to_string(source_path(day))?)
+            .ok_or(anyhow!("Cannot find example input for day {}", day))?,
+        InputKind::Real => fs::read_to_string(input_path(day))?,
+    };
+
+    Ok(String::from(trim_newlines(&input)))
+}
+
+/// Extracts the EXAMPLE_INPUT constant from the tests of a solver source.
+fn extract_example(source: &str) -> Option<String> {
+    let (_, rest) = source.split_once("const EXAMPLE_INPUT: &str = ")?;
+
+    if let Some(rest) = rest.strip_prefix("r\"") {
+        let (example, _) = rest.split_once("\";")?;
+
+        return Some(String::from(example));
+    }
+
+    let rest = rest.strip_prefix("\"")?;
+    let (example, _) = rest.split_once("\";")?;
+
+    Some(String::from(example))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_raw_example() {
+        let source = "mod tests {\n    const EXAMPLE_INPUT: &str = r\"\n1 2\n3 4\n\";\n}\n";
+
+        assert_eq!(extract_example(source).as_deref(), Some("\n1 2\n3 4\n"));
+    }
+
+    #[test]
+    fn extract_inline_example() {
+        let source = "    const EXAMPLE_INPUT: &str = \">><<\";\n";
+
+        assert_eq!(extract_example(source).as_deref(), Some(">><<"));
+    }
+
+    #[test]
+    fn every_day_has_example() {
+        for day in 1..=DAY_COUNT {
+            assert!(load_input(day, InputKind::Example).is_ok(), "day {day}");
+        }
+    }
+
+    #[test]
+    fn extract_missing_example() {
+        assert_eq!(extract_example("fn main() {}"), None);
+    }
+}
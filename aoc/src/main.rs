@@ -0,0 +1,32 @@
+mod bench;
+mod days;
+mod report;
+mod runner;
+mod tui;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Browses days, inputs and results in a terminal UI.
+    Tui,
+    /// Times each part over several runs, optionally with its memory usage.
+    Bench(bench::BenchArgs),
+    /// Writes a Markdown or HTML report of every answer, or merges several reports.
+    Report(report::ReportArgs),
+}
+
+fn main() -> Result<()> {
+    match Args::parse().command {
+        Command::Tui => tui::run(),
+        Command::Bench(args) => bench::run(args),
+        Command::Report(args) => report::run(args),
+    }
+}
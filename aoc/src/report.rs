@@ -0,0 +1,516 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Result, anyhow};
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    days::{DAY_COUNT, InputKind, WORKSPACE_DIR, load_input},
+    runner,
+};
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Name to put on the report, e.g. the team member who ran it.
+    #[arg(short, long, default_value = "me")]
+    name: String,
+
+    /// Days to report, e.g. "1,5,7". Reports every day if omitted.
+    #[arg(short, long, value_delimiter = ',')]
+    days: Vec<u8>,
+
+    /// Reports against the example input instead of the real input.
+    #[arg(long)]
+    example: bool,
+
+    /// Writes the raw report as JSON, to share and merge later.
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// Merges these JSON reports into a comparison table instead of running the solvers.
+    #[arg(long, num_args = 1.., value_name = "JSON_FILE")]
+    merge: Vec<PathBuf>,
+
+    /// Output format. Guessed from the output file extension if omitted.
+    #[arg(short, long)]
+    format: Option<Format>,
+
+    /// Writes the report to a file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Markdown,
+    Html,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Report {
+    name: String,
+    /// Seconds since the Unix epoch.
+    created: u64,
+    entries: Vec<Entry>,
+}
+
+/// The result of one part, with enough context to compare it with someone else's.
+#[derive(Deserialize, Serialize)]
+struct Entry {
+    day: u8,
+    part: u8,
+    input: String,
+    input_hash: Option<String>,
+    commit: Option<String>,
+    answer: Option<String>,
+    error: Option<String>,
+    elapsed_ns: Option<u64>,
+}
+
+/// Runs every requested part, or merges existing reports, and writes the result.
+pub fn run(args: ReportArgs) -> Result<()> {
+    let format = match (args.format, &args.output) {
+        (Some(format), _) => format,
+        (None, Some(path)) if path.extension().is_some_and(|ext| ext == "html") => Format::Html,
+        _ => Format::Markdown,
+    };
+
+    let rendered = if args.merge.is_empty() {
+        let report = create_report(&args)?;
+
+        if let Some(path) = &args.json {
+            fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        }
+
+        render(&[report], format)
+    } else {
+        let reports = args
+            .merge
+            .iter()
+            .map(|path| read_report(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        render(&reports, format)
+    };
+
+    match &args.output {
+        Some(path) => fs::write(path, rendered)?,
+        None => print!("{rendered}"),
+    }
+
+    Ok(())
+}
+
+fn read_report(path: &Path) -> Result<Report> {
+    serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|error| anyhow!("Cannot read report {}: {}", path.display(), error))
+}
+
+fn create_report(args: &ReportArgs) -> Result<Report> {
+    let days = if args.days.is_empty() {
+        (1..=DAY_COUNT).collect()
+    } else {
+        args.days.clone()
+    };
+    let kind = if args.example {
+        InputKind::Example
+    } else {
+        InputKind::Real
+    };
+
+    let mut entries = Vec::new();
+    for day in days {
+        let input_hash = load_input(day, kind).ok().map(|input| hash_input(&input));
+        let commit = solver_commit(day);
+
+        for part in 1..=2 {
+            let (answer, error, elapsed_ns) = match runner::run(day, part, kind, false) {
+                Ok(run) => match run.answer {
+                    Ok(answer) => (Some(answer), None, Some(run.elapsed.as_nanos() as u64)),
+                    Err(error) => (None, Some(error), None),
+                },
+                Err(error) => (None, Some(error.to_string()), None),
+            };
+
+            entries.push(Entry {
+                day,
+                part,
+                input: kind.to_string(),
+                input_hash: input_hash.clone(),
+                commit: commit.clone(),
+                answer,
+                error,
+                elapsed_ns,
+            });
+        }
+    }
+
+    Ok(Report {
+        name: args.name.clone(),
+        created: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        entries,
+    })
+}
+
+/// Hashes the trimmed input, so that the same puzzle input gives the same hash on any machine.
+fn hash_input(input: &str) -> String {
+    Sha256::digest(input.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Gets the last commit that touched a day's solver, marked if the solver has local changes.
+fn solver_commit(day: u8) -> Option<String> {
+    let dir = format!("day-{day}");
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(WORKSPACE_DIR)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let commit =
+        git(&["log", "-1", "--format=%h", "--", &dir]).filter(|commit| !commit.is_empty())?;
+    let dirty =
+        git(&["status", "--porcelain", "--", &dir]).is_some_and(|status| !status.is_empty());
+
+    Some(if dirty {
+        format!("{commit}-dirty")
+    } else {
+        commit
+    })
+}
+
+/// A table of rows by day and part, with one group of cells per report.
+struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+fn build_table(reports: &[Report]) -> Table {
+    let single = reports.len() == 1;
+
+    let mut headers = vec![String::from("Day"), String::from("Part")];
+    for report in reports {
+        if single {
+            headers.extend(["Answer", "Time", "Input hash", "Commit"].map(String::from));
+        } else {
+            headers.push(report.name.clone());
+        }
+    }
+    if !single {
+        headers.push(String::from("Check"));
+    }
+
+    let mut keys = reports
+        .iter()
+        .flat_map(|report| report.entries.iter().map(|entry| (entry.day, entry.part)))
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+
+    let rows = keys
+        .into_iter()
+        .map(|(day, part)| {
+            let entries = reports
+                .iter()
+                .map(|report| {
+                    report
+                        .entries
+                        .iter()
+                        .find(|entry| entry.day == day && entry.part == part)
+                })
+                .collect::<Vec<_>>();
+
+            let mut row = vec![day.to_string(), part.to_string()];
+            for entry in &entries {
+                if single {
+                    row.extend(format_entry_cells(*entry));
+                } else {
+                    row.push(format_entry_summary(*entry));
+                }
+            }
+            if !single {
+                row.push(check_entries(&entries));
+            }
+
+            row
+        })
+        .collect();
+
+    Table { headers, rows }
+}
+
+fn format_entry_cells(entry: Option<&Entry>) -> [String; 4] {
+    let Some(entry) = entry else {
+        return [
+            String::from("-"),
+            String::new(),
+            String::new(),
+            String::new(),
+        ];
+    };
+
+    [
+        format_answer(entry),
+        entry.elapsed_ns.map(format_elapsed).unwrap_or_default(),
+        entry
+            .input_hash
+            .as_deref()
+            .map(short_hash)
+            .unwrap_or_default(),
+        entry.commit.clone().unwrap_or_default(),
+    ]
+}
+
+/// Formats an entry as a single comparison cell, e.g. "13052 (1.20ms, input 3f2a9c1e)".
+fn format_entry_summary(entry: Option<&Entry>) -> String {
+    let Some(entry) = entry else {
+        return String::from("-");
+    };
+
+    let mut details = Vec::new();
+    if let Some(elapsed_ns) = entry.elapsed_ns {
+        details.push(format_elapsed(elapsed_ns));
+    }
+    if let Some(input_hash) = &entry.input_hash {
+        details.push(format!("input {}", short_hash(input_hash)));
+    }
+    if let Some(commit) = &entry.commit {
+        details.push(format!("commit {commit}"));
+    }
+
+    format!("{} ({})", format_answer(entry), details.join(", "))
+}
+
+fn format_answer(entry: &Entry) -> String {
+    match (&entry.answer, &entry.error) {
+        (Some(answer), _) => answer.clone(),
+        (None, Some(error)) => format!("error: {error}"),
+        (None, None) => String::from("-"),
+    }
+}
+
+fn format_elapsed(elapsed_ns: u64) -> String {
+    format!("{:.2?}", Duration::from_nanos(elapsed_ns))
+}
+
+fn short_hash(hash: &str) -> String {
+    hash.chars().take(8).collect()
+}
+
+/// Flags answers that differ even though the inputs are the same.
+fn check_entries(entries: &[Option<&Entry>]) -> String {
+    let answered = entries
+        .iter()
+        .flatten()
+        .filter(|entry| entry.answer.is_some())
+        .collect::<Vec<_>>();
+
+    let mismatch = answered.iter().enumerate().any(|(index, a)| {
+        answered[index + 1..]
+            .iter()
+            .any(|b| a.input_hash.is_some() && a.input_hash == b.input_hash && a.answer != b.answer)
+    });
+
+    if mismatch {
+        String::from("mismatch")
+    } else {
+        String::from("ok")
+    }
+}
+
+fn render(reports: &[Report], format: Format) -> String {
+    let title = match reports {
+        [report] => format!("Advent of Code 2022 report: {}", report.name),
+        _ => format!(
+            "Advent of Code 2022 comparison: {}",
+            reports
+                .iter()
+                .map(|report| report.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let table = build_table(reports);
+
+    match format {
+        Format::Markdown => render_markdown(&title, &table),
+        Format::Html => render_html(&title, &table),
+    }
+}
+
+fn render_markdown(title: &str, table: &Table) -> String {
+    // Cells cannot span lines, so multi-line errors are joined with line breaks.
+    let escape = |cell: &String| {
+        cell.replace("|", "\\|")
+            .replace("\r\n", "\n")
+            .replace("\n", "<br>")
+    };
+    let row = |cells: &[String]| {
+        format!(
+            "| {} |\n",
+            cells.iter().map(escape).collect::<Vec<_>>().join(" | ")
+        )
+    };
+
+    let mut markdown = format!("# {title}\n\n");
+    markdown += &row(&table.headers);
+    markdown += &format!("|{}\n", " :- |".repeat(table.headers.len()));
+    for cells in &table.rows {
+        markdown += &row(cells);
+    }
+
+    markdown
+}
+
+fn render_html(title: &str, table: &Table) -> String {
+    let escape = |cell: &str| {
+        cell.replace("&", "&amp;")
+            .replace("<", "&lt;")
+            .replace(">", "&gt;")
+            .replace("\"", "&quot;")
+    };
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n<style>\n\
+         body {{ font-family: sans-serif; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; font-family: monospace; }}\n\
+         th {{ background: #eee; }}\n\
+         </style>\n</head>\n<body>\n<h1>{0}</h1>\n<table>\n",
+        escape(title)
+    );
+
+    html += "<tr>";
+    for header in &table.headers {
+        html += &format!("<th>{}</th>", escape(header));
+    }
+    html += "</tr>\n";
+
+    for cells in &table.rows {
+        html += "<tr>";
+        for cell in cells {
+            html += &format!("<td>{}</td>", escape(cell));
+        }
+        html += "</tr>\n";
+    }
+
+    html += "</table>\n</body>\n</html>\n";
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(day: u8, part: u8, answer: &str, input_hash: &str) -> Entry {
+        Entry {
+            day,
+            part,
+            input: String::from("real"),
+            input_hash: Some(String::from(input_hash)),
+            commit: Some(String::from("abc1234")),
+            answer: Some(String::from(answer)),
+            error: None,
+            elapsed_ns: Some(1_500_000),
+        }
+    }
+
+    fn report(name: &str, entries: Vec<Entry>) -> Report {
+        Report {
+            name: String::from(name),
+            created: 0,
+            entries,
+        }
+    }
+
+    #[test]
+    fn render_single_markdown() {
+        let reports = [report(
+            "alice",
+            vec![entry(1, 1, "24000", "0123456789abcdef")],
+        )];
+
+        assert_eq!(
+            render(&reports, Format::Markdown),
+            "# Advent of Code 2022 report: alice\n\n\
+             | Day | Part | Answer | Time | Input hash | Commit |\n\
+             | :- | :- | :- | :- | :- | :- |\n\
+             | 1 | 1 | 24000 | 1.50ms | 01234567 | abc1234 |\n"
+        );
+    }
+
+    #[test]
+    fn merge_flags_mismatch() {
+        let reports = [
+            report(
+                "alice",
+                vec![entry(1, 1, "24000", "aaaa"), entry(1, 2, "45000", "aaaa")],
+            ),
+            report(
+                "bob",
+                vec![entry(1, 1, "24000", "aaaa"), entry(1, 2, "44999", "aaaa")],
+            ),
+            report("carol", vec![entry(1, 1, "70000", "bbbb")]),
+        ];
+
+        let table = build_table(&reports);
+
+        assert_eq!(
+            table.headers,
+            ["Day", "Part", "alice", "bob", "carol", "Check"]
+        );
+        assert_eq!(table.rows[0].last().unwrap(), "ok");
+        assert_eq!(table.rows[1][4], "-");
+        assert_eq!(table.rows[1].last().unwrap(), "mismatch");
+    }
+
+    #[test]
+    fn escape_multi_line_markdown() {
+        let mut failed = entry(2, 1, "", "aaaa");
+        failed.answer = None;
+        failed.elapsed_ns = None;
+        failed.error = Some(String::from("Cannot parse\n\nCaused by:\n    a | b"));
+
+        let markdown = render(&[report("alice", vec![failed])], Format::Markdown);
+
+        assert!(markdown.ends_with(
+            "| 2 | 1 | error: Cannot parse<br><br>Caused by:<br>    a \\| b |  | aaaa | abc1234 |\n"
+        ));
+        assert_eq!(markdown.lines().count(), 5);
+    }
+
+    #[test]
+    fn escape_html() {
+        let reports = [report("<script>", vec![entry(5, 1, "a&b", "aaaa")])];
+
+        let html = render(&reports, Format::Html);
+
+        assert!(html.contains("<title>Advent of Code 2022 report: &lt;script&gt;</title>"));
+        assert!(html.contains("<td>a&amp;b</td>"));
+    }
+
+    #[test]
+    fn round_trip_json() -> Result<()> {
+        let json = serde_json::to_string(&report("alice", vec![entry(1, 1, "24000", "aaaa")]))?;
+
+        let report: Report = serde_json::from_str(&json)?;
+
+        assert_eq!(report.name, "alice");
+        assert_eq!(report.entries[0].answer.as_deref(), Some("24000"));
+
+        Ok(())
+    }
+}
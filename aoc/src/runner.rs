@@ -0,0 +1,137 @@
+use std::{
+    env,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+
+use cli::MemoryProfile;
+
+use crate::days::{InputKind, WORKSPACE_DIR, load_input};
+
+/// The outcome of running one part of a day's solver.
+pub struct Run {
+    pub answer: Result<String, String>,
+    /// Everything the solver printed before its answer, e.g. a rendered grid.
+    pub output: String,
+    pub elapsed: Duration,
+    /// Reported by the solver when run with `--profile-mem`.
+    pub memory: Option<MemoryProfile>,
+}
+
+/// Gets the path to the solver binary of a day, built alongside this binary.
+fn solver_path(day: u8) -> Result<PathBuf> {
+    let exe = env::current_exe()?;
+    let dir = exe
+        .parent()
+        .ok_or(anyhow!("Cannot find directory of {}", exe.display()))?;
+
+    let path = dir.join(format!("day-{day}{}", env::consts::EXE_SUFFIX));
+    if !path.exists() {
+        return Err(anyhow!(
+            "Cannot find solver for day {} (run `cargo build --workspace` first)",
+            day
+        ));
+    }
+
+    Ok(path)
+}
+
+/// Runs one part of a day's solver against an input, feeding the input through stdin.
+pub fn run(day: u8, part: u8, kind: InputKind, profile_mem: bool) -> Result<Run> {
+    let input = load_input(day, kind)?;
+
+    let mut command = Command::new(solver_path(day)?);
+    command
+        .current_dir(WORKSPACE_DIR)
+        .args([&part.to_string(), "-i", "-"]);
+    if profile_mem {
+        command.arg("--profile-mem");
+    }
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let start = Instant::now();
+
+    child
+        .stdin
+        .take()
+        .ok_or(anyhow!("Cannot open stdin of day {}", day))?
+        .write_all(input.as_bytes())?;
+
+    let result = child.wait_with_output()?;
+    let elapsed = start.elapsed();
+
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    let memory = stderr.lines().find_map(MemoryProfile::from_line);
+
+    let stdout = String::from_utf8_lossy(&result.stdout);
+    let (output, answer) = split_answer(&stdout);
+    let answer = match answer {
+        _ if !result.status.success() => {
+            Err(format!("Exited with {}: {}", result.status, stderr.trim()))
+        }
+        Some(answer) => answer,
+        None => Err(String::from("No answer printed")),
+    };
+
+    Ok(Run {
+        answer,
+        output,
+        elapsed,
+        memory,
+    })
+}
+
+/// Splits solver stdout into the preceding output and the final `Ok(..)` or `Err(..)` line.
+fn split_answer(stdout: &str) -> (String, Option<Result<String, String>>) {
+    let stdout = stdout.trim_end_matches("\n");
+    let (output, last) = stdout.rsplit_once("\n").unwrap_or(("", stdout));
+
+    let answer = if let Some(answer) = last.strip_prefix("Ok(").and_then(|s| s.strip_suffix(")")) {
+        Some(Ok(String::from(answer.trim_matches('"'))))
+    } else if !last.is_empty() {
+        // Errors from get_part are printed without the Err wrapper.
+        let error = last.strip_prefix("Err(").and_then(|s| s.strip_suffix(")"));
+        Some(Err(String::from(error.unwrap_or(last))))
+    } else {
+        None
+    };
+
+    (String::from(output), answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_plain_answer() {
+        let (output, answer) = split_answer("Ok(24000)\n");
+
+        assert_eq!(output, "");
+        assert_eq!(answer, Some(Ok(String::from("24000"))));
+    }
+
+    #[test]
+    fn split_answer_after_grid() {
+        let (output, answer) = split_answer("##..\n..##\nOk(\"##..\\n..##\")\n");
+
+        assert_eq!(output, "##..\n..##");
+        assert_eq!(answer, Some(Ok(String::from("##..\\n..##"))));
+    }
+
+    #[test]
+    fn split_error() {
+        let (_, answer) = split_answer("Err(Cannot find max overall)\n");
+
+        assert_eq!(answer, Some(Err(String::from("Cannot find max overall"))));
+    }
+}
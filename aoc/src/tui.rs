@@ -0,0 +1,333 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+};
+
+use crate::{
+    days::{DAY_COUNT, InputKind, load_input},
+    runner::{self, Run},
+};
+
+/// Runs the explorer until the user quits.
+pub fn run() -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::new().event_loop(&mut terminal);
+    ratatui::restore();
+
+    result
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Focus {
+    Days,
+    Input,
+    Output,
+}
+
+impl Focus {
+    fn next(self) -> Self {
+        match self {
+            Focus::Days => Focus::Input,
+            Focus::Input => Focus::Output,
+            Focus::Output => Focus::Days,
+        }
+    }
+}
+
+/// Identifies a run by day, part and input kind.
+type RunKey = (u8, u8, InputKind);
+
+struct App {
+    days: ListState,
+    kind: InputKind,
+    focus: Focus,
+    show_log: bool,
+    input: Result<String, String>,
+    input_scroll: u16,
+    output_scroll: u16,
+    log_scroll: u16,
+    results: HashMap<RunKey, Run>,
+    /// The part that finished last for each day and input kind.
+    latest: HashMap<(u8, InputKind), u8>,
+    pending: Vec<RunKey>,
+    log: Vec<String>,
+    sender: Sender<(RunKey, Result<Run, String>)>,
+    receiver: Receiver<(RunKey, Result<Run, String>)>,
+    quit: bool,
+}
+
+impl App {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let mut app = App {
+            days: ListState::default().with_selected(Some(0)),
+            kind: InputKind::Example,
+            focus: Focus::Days,
+            show_log: false,
+            input: Ok(String::new()),
+            input_scroll: 0,
+            output_scroll: 0,
+            log_scroll: 0,
+            results: HashMap::new(),
+            latest: HashMap::new(),
+            pending: Vec::new(),
+            log: Vec::new(),
+            sender,
+            receiver,
+            quit: false,
+        };
+        app.reload_input();
+
+        app
+    }
+
+    fn day(&self) -> u8 {
+        self.days.selected().unwrap_or(0) as u8 + 1
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            while let Ok((key, run)) = self.receiver.try_recv() {
+                self.finish_run(key, run);
+            }
+
+            if event::poll(Duration::from_millis(100))?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key.code);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('1') => self.start_run(1),
+            KeyCode::Char('2') => self.start_run(2),
+            KeyCode::Char('e') => {
+                self.kind = self.kind.toggle();
+                self.reload_input();
+            }
+            KeyCode::Char('l') => self.show_log = !self.show_log,
+            KeyCode::Tab => self.focus = self.focus.next(),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll(1),
+            KeyCode::PageUp => self.scroll(-10),
+            KeyCode::PageDown => self.scroll(10),
+            _ => (),
+        }
+    }
+
+    /// Moves the selection or scroll position of the focused pane.
+    fn scroll(&mut self, delta: i16) {
+        if self.show_log {
+            self.log_scroll = self.log_scroll.saturating_add_signed(delta);
+            return;
+        }
+
+        match self.focus {
+            Focus::Days => {
+                let day = (self.day() as i16 - 1 + delta).clamp(0, DAY_COUNT as i16 - 1);
+                self.days.select(Some(day as usize));
+                self.reload_input();
+            }
+            Focus::Input => self.input_scroll = self.input_scroll.saturating_add_signed(delta),
+            Focus::Output => self.output_scroll = self.output_scroll.saturating_add_signed(delta),
+        }
+    }
+
+    fn reload_input(&mut self) {
+        self.input = load_input(self.day(), self.kind).map_err(|error| error.to_string());
+        self.input_scroll = 0;
+        self.output_scroll = 0;
+    }
+
+    /// Runs a part of the selected day in the background.
+    fn start_run(&mut self, part: u8) {
+        let key = (self.day(), part, self.kind);
+        if self.pending.contains(&key) {
+            return;
+        }
+        self.pending.push(key);
+
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let (day, part, kind) = key;
+            let run = runner::run(day, part, kind, false).map_err(|error| error.to_string());
+
+            // The receiver only goes away when the app quits.
+            let _ = sender.send((key, run));
+        });
+    }
+
+    fn finish_run(&mut self, key: RunKey, run: Result<Run, String>) {
+        self.pending.retain(|pending| *pending != key);
+
+        let (day, part, kind) = key;
+        match run {
+            Ok(run) => {
+                let answer = match &run.answer {
+                    Ok(answer) => answer.clone(),
+                    Err(error) => format!("error: {error}"),
+                };
+                self.log.push(format!(
+                    "day {day} part {part} ({kind}) in {:.2?}: {answer}",
+                    run.elapsed
+                ));
+                self.results.insert(key, run);
+                self.latest.insert((day, kind), part);
+            }
+            Err(error) => self
+                .log
+                .push(format!("day {day} part {part} ({kind}) failed: {error}")),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        frame.render_widget(
+            Line::from("q quit | tab focus | ↑↓ move | 1/2 run part | e example/real | l log")
+                .dim(),
+            help,
+        );
+
+        if self.show_log {
+            self.draw_log(frame, main);
+            return;
+        }
+
+        let [days, input, right] = Layout::horizontal([
+            Constraint::Length(12),
+            Constraint::Percentage(45),
+            Constraint::Min(0),
+        ])
+        .areas(main);
+        let [answers, output] =
+            Layout::vertical([Constraint::Length(6), Constraint::Min(0)]).areas(right);
+
+        self.draw_days(frame, days);
+        self.draw_input(frame, input);
+        self.draw_answers(frame, answers);
+        self.draw_output(frame, output);
+    }
+
+    fn block(&self, title: String, focus: Focus) -> Block<'static> {
+        let block = Block::bordered().title(title);
+
+        if self.focus == focus {
+            block.border_style(Style::new().yellow())
+        } else {
+            block
+        }
+    }
+
+    fn draw_days(&mut self, frame: &mut Frame, area: Rect) {
+        let items = (1..=DAY_COUNT).map(|day| {
+            let solved = (1..=2).any(|part| {
+                self.results
+                    .get(&(day, part, self.kind))
+                    .is_some_and(|run| run.answer.is_ok())
+            });
+
+            ListItem::new(format!("{} day {day}", if solved { "✓" } else { " " }))
+        });
+
+        let list = List::new(items)
+            .block(self.block(String::from("Days"), Focus::Days))
+            .highlight_style(Style::new().reversed());
+
+        frame.render_stateful_widget(list, area, &mut self.days);
+    }
+
+    fn draw_input(&self, frame: &mut Frame, area: Rect) {
+        let title = format!("Input ({})", self.kind);
+        let text = match &self.input {
+            Ok(input) => input.clone(),
+            Err(error) => error.clone(),
+        };
+
+        frame.render_widget(
+            Paragraph::new(text)
+                .block(self.block(title, Focus::Input))
+                .scroll((self.input_scroll, 0)),
+            area,
+        );
+    }
+
+    fn draw_answers(&self, frame: &mut Frame, area: Rect) {
+        let lines = (1..=2).map(|part| {
+            let key = (self.day(), part, self.kind);
+
+            let status = if self.pending.contains(&key) {
+                String::from("running...")
+            } else {
+                match self.results.get(&key) {
+                    Some(Run {
+                        answer: Ok(answer),
+                        elapsed,
+                        ..
+                    }) => format!("{answer} ({elapsed:.2?})"),
+                    Some(Run {
+                        answer: Err(error), ..
+                    }) => format!("error: {error}"),
+                    None => String::from("-"),
+                }
+            };
+
+            Line::from(format!("Part {part}: {status}"))
+        });
+
+        frame.render_widget(
+            Paragraph::new(lines.collect::<Vec<_>>()).block(Block::bordered().title("Answers")),
+            area,
+        );
+    }
+
+    /// Draws whatever the last run of the day printed before its answer, e.g. a rendered grid.
+    fn draw_output(&self, frame: &mut Frame, area: Rect) {
+        let output = self
+            .latest
+            .get(&(self.day(), self.kind))
+            .and_then(|part| self.results.get(&(self.day(), *part, self.kind)))
+            .map(|run| run.output.clone())
+            .unwrap_or_default();
+
+        frame.render_widget(
+            Paragraph::new(output)
+                .block(self.block(String::from("Output"), Focus::Output))
+                .scroll((self.output_scroll, 0)),
+            area,
+        );
+    }
+
+    fn draw_log(&self, frame: &mut Frame, area: Rect) {
+        let text = self.log.iter().map(|line| Line::from(line.as_str()));
+
+        frame.render_widget(
+            Paragraph::new(text.collect::<Vec<_>>())
+                .block(Block::bordered().title("Run log"))
+                .scroll((self.log_scroll, 0)),
+            area,
+        );
+    }
+}
@@ -1,33 +1,138 @@
-use std::fs;
+mod profile;
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    sync::atomic::Ordering,
+};
 
 use anyhow::{Result, anyhow};
-use clap::Parser;
+use clap::{Parser, Subcommand};
+
+pub use profile::{CountingAllocator, MemoryProfile, PROFILE_MEM_PREFIX, profile};
 
 #[derive(Parser)]
 struct Args {
     part: u8,
 
+    /// Path to the puzzle input, or "-" to read it from stdin.
     #[arg(short = 'i', long)]
     input: Option<String>,
+
+    /// Reports allocations and peak heap usage of the part to stderr.
+    #[arg(long)]
+    profile_mem: bool,
+}
+
+#[derive(Parser)]
+#[command(subcommand_precedence_over_arg = true)]
+struct TaskArgs<M: Subcommand> {
+    part: Option<u8>,
+
+    /// Path to the puzzle input, or "-" to read it from stdin.
+    #[arg(short = 'i', long, global = true)]
+    input: Option<String>,
+
+    /// Reports allocations and peak heap usage of the part to stderr.
+    #[arg(long, global = true)]
+    profile_mem: bool,
+
+    #[command(subcommand)]
+    mode: Option<M>,
+}
+
+pub enum Part<T = String> {
+    Part1(T),
+    Part2(T),
 }
 
-pub enum Part {
-    Part1(String),
-    Part2(String),
+/// Either one of the puzzle parts, or a day-specific mode [M] with the input.
+pub enum Task<M, T = String> {
+    Part(Part<T>),
+    Mode(M, T),
 }
 
 /// Gets the [Part] to execute.
 pub fn get_part(default_input: &str) -> Result<Part> {
-    let args = Args::parse();
+    let args = parse_args();
+
+    let path = args.input.unwrap_or(String::from(default_input));
+    let input = String::from(trim_newlines(&read_input(&path)?));
+
+    select_part(args.part, input)
+}
+
+/// Gets the [Task] to execute, where the day defines its extra modes as a [Subcommand].
+pub fn get_task<M: Subcommand>(default_input: &str) -> Result<Task<M>> {
+    let (part, mode, path) = parse_task_args(default_input);
+    let input = String::from(trim_newlines(&read_input(&path)?));
+
+    select_task(part, mode, input)
+}
+
+/// Gets the [Task] to execute, with a reader over the input instead of the whole input.
+///
+/// The input is not trimmed, so the reader should skip blank lines where it matters.
+pub fn get_task_reader<M: Subcommand>(default_input: &str) -> Result<Task<M, Box<dyn BufRead>>> {
+    let (part, mode, path) = parse_task_args(default_input);
+
+    select_task(part, mode, open_input(&path)?)
+}
+
+fn parse_task_args<M: Subcommand>(default_input: &str) -> (Option<u8>, Option<M>, String) {
+    let args = TaskArgs::<M>::parse();
+
+    profile::PROFILE_MEM.store(args.profile_mem, Ordering::Relaxed);
 
     let path = args.input.unwrap_or(String::from(default_input));
-    let input = String::from(trim_newlines(&fs::read_to_string(path)?));
 
-    match args.part {
+    (args.part, args.mode, path)
+}
+
+fn select_task<M, T>(part: Option<u8>, mode: Option<M>, input: T) -> Result<Task<M, T>> {
+    match (part, mode) {
+        (Some(part), Some(_)) => Err(anyhow!("Cannot run part {} together with a mode", part)),
+        (None, Some(mode)) => Ok(Task::Mode(mode, input)),
+        (Some(part), None) => Ok(Task::Part(select_part(part, input)?)),
+        (None, None) => Err(anyhow!("Missing part number or mode")),
+    }
+}
+
+fn parse_args() -> Args {
+    let args = Args::parse();
+
+    profile::PROFILE_MEM.store(args.profile_mem, Ordering::Relaxed);
+
+    args
+}
+
+fn select_part<T>(part: u8, input: T) -> Result<Part<T>> {
+    match part {
         1 => Ok(Part::Part1(input)),
         2 => Ok(Part::Part2(input)),
-        _ => Err(anyhow!("Invalid part number: {}", args.part)),
+        _ => Err(anyhow!("Invalid part number: {}", part)),
+    }
+}
+
+/// Reads the input from a file, or from stdin if path is "-".
+fn read_input(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+
+        return Ok(input);
     }
+
+    Ok(fs::read_to_string(path)?)
+}
+
+/// Opens a reader over a file, or over stdin if path is "-".
+fn open_input(path: &str) -> Result<Box<dyn BufRead>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    Ok(Box::new(BufReader::new(File::open(path)?)))
 }
 
 /// Trims newlines from the start and the end of the input string.
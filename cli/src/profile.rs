@@ -0,0 +1,172 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static HEAP_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_HEAP_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) static PROFILE_MEM: AtomicBool = AtomicBool::new(false);
+
+/// A [GlobalAlloc] that counts allocations on top of [System].
+///
+/// Counting only happens with `--profile-mem`. Otherwise each call costs one relaxed load.
+///
+/// Install it in a day's binary so that `--profile-mem` has something to report:
+///
+/// ```
+/// # use cli::CountingAllocator;
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// # fn main() {}
+/// ```
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() && PROFILE_MEM.load(Ordering::Relaxed) {
+            record_alloc(layout.size());
+        }
+
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() && PROFILE_MEM.load(Ordering::Relaxed) {
+            record_alloc(layout.size());
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        if PROFILE_MEM.load(Ordering::Relaxed) {
+            record_dealloc(layout.size());
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() && PROFILE_MEM.load(Ordering::Relaxed) {
+            // Count a realloc as freeing the old block and allocating the new one.
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+
+        new_ptr
+    }
+}
+
+fn record_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
+
+    let heap = HEAP_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_HEAP_BYTES.fetch_max(heap, Ordering::Relaxed);
+}
+
+/// Blocks allocated before counting started were never added, so the count stops at zero.
+fn record_dealloc(size: usize) {
+    let _ = HEAP_BYTES.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |heap| {
+        Some(heap.saturating_sub(size))
+    });
+}
+
+/// Memory usage counted by [CountingAllocator] over one run.
+pub struct MemoryProfile {
+    pub allocations: usize,
+    pub allocated_bytes: usize,
+    /// The most heap memory in use at any one time, including the input.
+    pub peak_heap_bytes: usize,
+}
+
+/// Prefix of the line that [profile] writes to stderr.
+pub const PROFILE_MEM_PREFIX: &str = "profile-mem:";
+
+impl MemoryProfile {
+    /// Formats the profile as the line that [profile] writes to stderr.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{PROFILE_MEM_PREFIX} allocations={} allocated_bytes={} peak_heap_bytes={}",
+            self.allocations, self.allocated_bytes, self.peak_heap_bytes
+        )
+    }
+
+    /// Parses a line written by [profile], e.g. when read back from a solver's stderr.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut profile = MemoryProfile {
+            allocations: 0,
+            allocated_bytes: 0,
+            peak_heap_bytes: 0,
+        };
+
+        for field in line.strip_prefix(PROFILE_MEM_PREFIX)?.split_whitespace() {
+            let (name, value) = field.split_once("=")?;
+            let value = value.parse().ok()?;
+
+            match name {
+                "allocations" => profile.allocations = value,
+                "allocated_bytes" => profile.allocated_bytes = value,
+                "peak_heap_bytes" => profile.peak_heap_bytes = value,
+                _ => return None,
+            }
+        }
+
+        Some(profile)
+    }
+}
+
+/// Runs a part, writing its [MemoryProfile] to stderr if `--profile-mem` was given.
+///
+/// The counts are only meaningful if the binary installs [CountingAllocator].
+pub fn profile<T>(part: impl FnOnce() -> T) -> T {
+    if !PROFILE_MEM.load(Ordering::Relaxed) {
+        return part();
+    }
+
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    ALLOCATED_BYTES.store(0, Ordering::Relaxed);
+    PEAK_HEAP_BYTES.store(HEAP_BYTES.load(Ordering::Relaxed), Ordering::Relaxed);
+
+    let result = part();
+
+    let profile = MemoryProfile {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        peak_heap_bytes: PEAK_HEAP_BYTES.load(Ordering::Relaxed),
+    };
+    eprintln!("{}", profile.to_line());
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_line() {
+        let line = MemoryProfile {
+            allocations: 3,
+            allocated_bytes: 1024,
+            peak_heap_bytes: 512,
+        }
+        .to_line();
+
+        let profile = MemoryProfile::from_line(&line).unwrap();
+
+        assert_eq!(profile.allocations, 3);
+        assert_eq!(profile.allocated_bytes, 1024);
+        assert_eq!(profile.peak_heap_bytes, 512);
+    }
+
+    #[test]
+    fn reject_other_lines() {
+        assert!(MemoryProfile::from_line("thread 'main' panicked").is_none());
+    }
+}
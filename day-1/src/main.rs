@@ -1,48 +1,308 @@
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Write, io::BufRead};
+
 use anyhow::{Result, anyhow};
+use clap::{Subcommand, ValueEnum};
+
+use cli::{CountingAllocator, Part, Task, get_task_reader, profile, trim_newlines};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Reports each elf's items, totals and ranking, and a histogram of totals.
+    Report {
+        #[arg(short, long, default_value = "table")]
+        format: Format,
 
-use cli::{Part, get_part};
+        /// Number of histogram bins.
+        #[arg(short, long, default_value_t = 10)]
+        bins: usize,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    /// One row per elf. The histogram is left out.
+    Csv,
+    Json,
+}
 
 fn main() {
-    match get_part("inputs/day-1.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task_reader::<Mode>("inputs/day-1.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(Mode::Report { format, bins }, input)) => match report(input, format, bins) {
+            Ok(report) => print!("{report}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
-fn part_1(input: String) -> Result<u32> {
+fn part_1(input: impl BufRead) -> Result<u32> {
+    top_calories(input, 1)?
+        .first()
+        .copied()
+        .ok_or(anyhow!("Cannot find max overall"))
+}
+
+fn part_2(input: impl BufRead) -> Result<u32> {
+    top_calories(input, 3)?
+        .into_iter()
+        .try_fold(0u32, u32::checked_add)
+        .ok_or(anyhow!("Sum of top 3 calories overflows u32"))
+}
+
+/// Finds the k largest calorie totals in descending order, reading one line at a time.
+///
+/// Only the k largest totals seen so far are kept, in a min-heap so the smallest of them is the
+/// one to drop.
+fn top_calories(mut input: impl BufRead, k: usize) -> Result<Vec<u32>> {
+    let mut top = BinaryHeap::with_capacity(k + 1);
+    let mut push = |total: u32| {
+        top.push(Reverse(total));
+        if top.len() > k {
+            top.pop();
+        }
+    };
+
+    let mut elf = 1;
+    let mut total = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        // A blank line ends a section. Repeated blank lines do not start new elves.
+        if line.is_empty() {
+            if let Some(total) = total.take() {
+                push(total);
+                elf += 1;
+            }
+            continue;
+        }
+
+        let calories = line.parse::<u32>()?;
+        let Some(sum) = total.unwrap_or(0u32).checked_add(calories) else {
+            return Err(anyhow!("Calories of elf {} overflow u32", elf));
+        };
+        total = Some(sum);
+    }
+    if let Some(total) = total {
+        push(total);
+    }
+
+    // Ascending order of Reverse is descending order of calories.
+    Ok(top
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(total)| total)
+        .collect())
+}
+
+/// The items carried by an elf, numbered from 1 in input order.
+struct Elf {
+    index: usize,
+    items: Vec<u32>,
+}
+
+struct ElfStats {
+    index: usize,
+    item_count: usize,
+    total: u32,
+    mean: f64,
+    max_item: u32,
+    /// 1 for the elf carrying the most calories. Elves with equal totals share a rank.
+    rank: usize,
+}
+
+/// A histogram bin covering the totals from start to end inclusive.
+struct Bin {
+    start: u64,
+    end: u64,
+    count: usize,
+}
+
+fn parse_elves(input: &str) -> Result<Vec<Elf>> {
     input
         .split_terminator("\n\n")
-        .map(|section| {
-            Ok(section
-                .lines()
-                .map(str::parse::<u32>)
-                .collect::<Result<Vec<_>, _>>()?
-                .into_iter()
-                .sum())
+        // As in top_calories, repeated blank lines do not start new elves.
+        .map(|section| section.trim_matches('\n'))
+        .filter(|section| !section.is_empty())
+        .enumerate()
+        .map(|(index, section)| {
+            Ok(Elf {
+                index: index + 1,
+                items: section
+                    .lines()
+                    .map(str::parse::<u32>)
+                    .collect::<Result<Vec<_>, _>>()?,
+            })
         })
-        .collect::<Result<Vec<_>>>()?
-        .into_iter()
-        .max()
-        .ok_or(anyhow!("Cannot find max overall"))
+        .collect()
 }
 
-fn part_2(input: String) -> Result<u32> {
-    let mut calories = input
-        .split_terminator("\n\n")
-        .map(|section| {
-            Ok(section
-                .lines()
-                .map(str::parse::<u32>)
-                .collect::<Result<Vec<_>, _>>()?
-                .into_iter()
-                .sum::<u32>())
+fn elf_stats(elves: &[Elf]) -> Result<Vec<ElfStats>> {
+    let totals = elves
+        .iter()
+        .map(|elf| {
+            let Some(total) = elf
+                .items
+                .iter()
+                .try_fold(0u32, |acc, item| acc.checked_add(*item))
+            else {
+                return Err(anyhow!("Calories of elf {} overflow u32", elf.index));
+            };
+
+            Ok(total)
         })
         .collect::<Result<Vec<_>>>()?;
 
-    calories.sort_by(|a, b| b.cmp(a)); // descending
+    let mut descending = totals.clone();
+    descending.sort_unstable_by_key(|total| Reverse(*total));
 
-    Ok(calories.into_iter().take(3).sum())
+    Ok(elves
+        .iter()
+        .zip(&totals)
+        .map(|(elf, total)| ElfStats {
+            index: elf.index,
+            item_count: elf.items.len(),
+            total: *total,
+            mean: if elf.items.is_empty() {
+                0.0
+            } else {
+                *total as f64 / elf.items.len() as f64
+            },
+            max_item: elf.items.iter().max().copied().unwrap_or(0),
+            rank: descending.partition_point(|other| other > total) + 1,
+        })
+        .collect())
+}
+
+/// Splits the range of totals into equal-width bins and counts the elves in each.
+fn histogram(stats: &[ElfStats], bins: usize) -> Vec<Bin> {
+    let (Some(min), Some(max)) = (
+        stats.iter().map(|elf| elf.total as u64).min(),
+        stats.iter().map(|elf| elf.total as u64).max(),
+    ) else {
+        return Vec::new();
+    };
+    // Equal totals all go in one bin, rather than the first of many bins of width 1.
+    let bins = if min == max { 1 } else { bins.max(1) as u64 };
+    let width = (max - min) / bins + 1;
+
+    let mut histogram = (0..bins)
+        .map(|bin| Bin {
+            start: min + bin * width,
+            end: min + (bin + 1) * width - 1,
+            count: 0,
+        })
+        .collect::<Vec<_>>();
+    for elf in stats {
+        histogram[((elf.total as u64 - min) / width) as usize].count += 1;
+    }
+
+    histogram
+}
+
+fn report(mut input: impl BufRead, format: Format, bins: usize) -> Result<String> {
+    let mut buffer = String::new();
+    input.read_to_string(&mut buffer)?;
+
+    let stats = elf_stats(&parse_elves(trim_newlines(&buffer))?)?;
+    let histogram = histogram(&stats, bins);
+
+    Ok(match format {
+        Format::Table => format_table(&stats, &histogram)?,
+        Format::Csv => format_csv(&stats)?,
+        Format::Json => format_json(&stats, &histogram)?,
+    })
+}
+
+fn format_table(stats: &[ElfStats], histogram: &[Bin]) -> Result<String> {
+    let mut table = String::new();
+
+    writeln!(
+        table,
+        "{:>5} {:>6} {:>10} {:>10} {:>10} {:>5}",
+        "Elf", "Items", "Total", "Mean", "Max item", "Rank"
+    )?;
+    for elf in stats {
+        writeln!(
+            table,
+            "{:>5} {:>6} {:>10} {:>10.2} {:>10} {:>5}",
+            elf.index, elf.item_count, elf.total, elf.mean, elf.max_item, elf.rank
+        )?;
+    }
+
+    writeln!(table, "\nHistogram of totals")?;
+    let max_count = histogram
+        .iter()
+        .map(|bin| bin.count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for bin in histogram {
+        // Scale bars to at most 40 characters wide.
+        let bar = "#".repeat(bin.count * 40 / max_count);
+        writeln!(
+            table,
+            "{:>10} - {:>10} | {bar} {}",
+            bin.start, bin.end, bin.count
+        )?;
+    }
+
+    Ok(table)
+}
+
+fn format_csv(stats: &[ElfStats]) -> Result<String> {
+    let mut csv = String::from("elf,items,total,mean,max_item,rank\n");
+
+    for elf in stats {
+        writeln!(
+            csv,
+            "{},{},{},{:.2},{},{}",
+            elf.index, elf.item_count, elf.total, elf.mean, elf.max_item, elf.rank
+        )?;
+    }
+
+    Ok(csv)
+}
+
+fn format_json(stats: &[ElfStats], histogram: &[Bin]) -> Result<String> {
+    let elves = stats
+        .iter()
+        .map(|elf| {
+            format!(
+                "{{\"elf\":{},\"items\":{},\"total\":{},\"mean\":{:.2},\"max_item\":{},\"rank\":{}}}",
+                elf.index, elf.item_count, elf.total, elf.mean, elf.max_item, elf.rank
+            )
+        })
+        .collect::<Vec<_>>();
+    let bins = histogram
+        .iter()
+        .map(|bin| {
+            format!(
+                "{{\"start\":{},\"end\":{},\"count\":{}}}",
+                bin.start, bin.end, bin.count
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut json = String::new();
+    writeln!(
+        json,
+        "{{\"elves\":[{}],\"histogram\":[{}]}}",
+        elves.join(","),
+        bins.join(",")
+    )?;
+
+    Ok(json)
 }
 
 #[cfg(test)]
@@ -68,15 +328,98 @@ mod tests {
 
     #[test]
     fn example_1() -> Result<()> {
-        assert_eq!(part_1(EXAMPLE_INPUT.trim().to_string())?, 24000);
+        assert_eq!(part_1(EXAMPLE_INPUT.trim().as_bytes())?, 24000);
 
         Ok(())
     }
 
     #[test]
     fn example_2() -> Result<()> {
-        assert_eq!(part_2(EXAMPLE_INPUT.trim().to_string())?, 45000);
+        assert_eq!(part_2(EXAMPLE_INPUT.trim().as_bytes())?, 45000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn top_k() -> Result<()> {
+        assert_eq!(
+            top_calories(EXAMPLE_INPUT.as_bytes(), 4)?,
+            [24000, 11000, 10000, 6000]
+        );
+        assert_eq!(top_calories(EXAMPLE_INPUT.as_bytes(), 10)?.len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stats() -> Result<()> {
+        let stats = elf_stats(&parse_elves(EXAMPLE_INPUT.trim())?)?;
+
+        assert_eq!(
+            stats.iter().map(|elf| elf.rank).collect::<Vec<_>>(),
+            [4, 5, 2, 1, 3]
+        );
+        assert_eq!(stats[0].item_count, 3);
+        assert_eq!(stats[0].mean, 2000.0);
+        assert_eq!(stats[3].max_item, 9000);
 
         Ok(())
     }
+
+    #[test]
+    fn histogram_counts() -> Result<()> {
+        let stats = elf_stats(&parse_elves(EXAMPLE_INPUT.trim())?)?;
+
+        let histogram = histogram(&stats, 2);
+
+        assert_eq!(
+            histogram
+                .iter()
+                .map(|bin| (bin.start, bin.end, bin.count))
+                .collect::<Vec<_>>(),
+            [(4000, 14000, 4), (14001, 24001, 1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn histogram_equal_totals() -> Result<()> {
+        let stats = elf_stats(&parse_elves("5\n\n2\n3\n\n5")?)?;
+
+        let histogram = histogram(&stats, 10);
+
+        assert_eq!(
+            histogram
+                .iter()
+                .map(|bin| (bin.start, bin.end, bin.count))
+                .collect::<Vec<_>>(),
+            [(5, 5, 3)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn repeated_blank_lines() -> Result<()> {
+        let input = "1000\n\n\n2000\n3000\n\n";
+
+        let stats = elf_stats(&parse_elves(input)?)?;
+
+        assert_eq!(
+            stats.iter().map(|elf| elf.total).collect::<Vec<_>>(),
+            top_calories(input.as_bytes(), 2)?
+                .into_iter()
+                .rev()
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn overflow() {
+        assert!(top_calories("4294967295\n1".as_bytes(), 1).is_err());
+        assert!(part_2("4294967295\n\n1".as_bytes()).is_err());
+    }
 }
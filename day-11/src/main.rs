@@ -1,13 +1,16 @@
-use std::collections::VecDeque;
+use std::{cmp::Reverse, collections::VecDeque};
 
 use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, get_part, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 fn main() {
     match get_part("inputs/day-11.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+        Ok(Part::Part1(input)) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Part::Part2(input)) => println!("{:?}", profile(|| part_2(input))),
         Err(error) => println!("{error:?}"),
     }
 }
@@ -17,7 +20,7 @@ fn part_1(input: String) -> Result<usize> {
 
     (0..20).for_each(|_| round(&mut monkeys, None));
 
-    monkeys.sort_by(|a, b| b.inspection_count.cmp(&a.inspection_count));
+    monkeys.sort_by_key(|monkey| Reverse(monkey.inspection_count));
     Ok(monkeys
         .into_iter()
         .take(2)
@@ -33,7 +36,7 @@ fn part_2(input: String) -> Result<usize> {
 
     (0..10000).for_each(|_| round(&mut monkeys, Some(modulus)));
 
-    monkeys.sort_by(|a, b| b.inspection_count.cmp(&a.inspection_count));
+    monkeys.sort_by_key(|monkey| Reverse(monkey.inspection_count));
     Ok(monkeys
         .into_iter()
         .take(2)
@@ -118,7 +121,7 @@ fn parse_monkey(input: &str) -> Result<Monkey> {
         return Err(anyhow!("Cannot split Test line: {}", lines[3]));
     };
     let divisor = divisor.parse::<u64>()?;
-    let test = Box::new(move |item: u64| item % divisor == 0);
+    let test = Box::new(move |item: u64| item.is_multiple_of(divisor));
 
     // true_target
 
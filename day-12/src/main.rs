@@ -1,12 +1,15 @@
 use anyhow::{Result, anyhow};
 use pathfinding::prelude::dijkstra;
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, get_part, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 fn main() {
     match get_part("inputs/day-12.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+        Ok(Part::Part1(input)) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Part::Part2(input)) => println!("{:?}", profile(|| part_2(input))),
         Err(error) => println!("{error:?}"),
     }
 }
@@ -2,12 +2,15 @@ use std::collections::HashSet;
 
 use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, get_part, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 fn main() {
     match get_part("inputs/day-17.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+        Ok(Part::Part1(input)) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Part::Part2(input)) => println!("{:?}", profile(|| part_2(input))),
         Err(error) => println!("{error:?}"),
     }
 }
@@ -118,10 +121,10 @@ fn part_2(input: String) -> Result<usize> {
 
         chamber.settle(rock, rock_pos);
 
-        if let Some(final_rock) = final_rock {
-            if index == final_rock {
-                break;
-            }
+        if let Some(final_rock) = final_rock
+            && index == final_rock
+        {
+            break;
         }
 
         // Compact every 25 rocks -- unless index is from 2000 until we find the cycle, compact
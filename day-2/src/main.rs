@@ -1,136 +1,782 @@
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, hash_map::Entry},
+    fs,
+    path::PathBuf,
+    str::FromStr,
+};
 
 use anyhow::{Result, anyhow};
+use clap::Subcommand;
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, Task, get_task, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Scores a strategy guide of "<opponent> <counter>" shape names.
+    Play {
+        /// Game definition file. Plays rock-paper-scissors if omitted.
+        #[arg(short, long)]
+        game: Option<PathBuf>,
+    },
+    /// Scores the guide under every reading of the X/Y/Z column.
+    Cipher {
+        /// Only lists the readings that give this total score.
+        #[arg(short, long)]
+        target: Option<u32>,
+    },
+    /// Finds the best moves against the opponent column under constraints.
+    Optimise {
+        /// Game definition file. Plays rock-paper-scissors if omitted.
+        #[arg(short, long)]
+        game: Option<PathBuf>,
+
+        /// Uses each shape at most this many times.
+        #[arg(short = 'k', long)]
+        max_uses: Option<usize>,
+
+        /// Never plays the same shape twice in a row.
+        #[arg(long)]
+        no_repeat: bool,
+    },
+}
 
 fn main() {
-    match get_part("inputs/day-2.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task::<Mode>("inputs/day-2.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(Mode::Play { game }, input)) => println!("{:?}", play(input, game)),
+        Ok(Task::Mode(Mode::Cipher { target }, input)) => match cipher(input, target) {
+            Ok(report) => print!("{report}"),
+            Err(error) => println!("{error:?}"),
+        },
+        Ok(Task::Mode(
+            Mode::Optimise {
+                game,
+                max_uses,
+                no_repeat,
+            },
+            input,
+        )) => match optimise(
+            input,
+            game,
+            Constraints {
+                max_uses,
+                no_repeat,
+            },
+        ) {
+            Ok(report) => print!("{report}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
 fn part_1(input: String) -> Result<u32> {
+    let game = Game::rock_paper_scissors();
+
     Ok(input
         .lines()
         .map(Round::from_str_part_1)
         .collect::<Result<Vec<_>>>()?
         .into_iter()
-        .map(Round::score)
+        .map(|round| round.score(&game))
         .sum())
 }
 
 fn part_2(input: String) -> Result<u32> {
+    let game = Game::rock_paper_scissors();
+
+    Ok(input
+        .lines()
+        .map(|line| Round::from_str_part_2(line, &game))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .map(|round| round.score(&game))
+        .sum())
+}
+
+fn play(input: String, game: Option<PathBuf>) -> Result<u32> {
+    let game = match game {
+        Some(path) => fs::read_to_string(path)?.parse::<Game>()?,
+        None => Game::rock_paper_scissors(),
+    };
+
     Ok(input
         .lines()
-        .map(Round::from_str_part_2)
+        .map(|line| Round::from_str_names(line, &game))
         .collect::<Result<Vec<_>>>()?
         .into_iter()
-        .map(Round::score)
+        .map(|round| round.score(&game))
         .sum())
 }
 
-#[derive(Clone, Copy, Eq, PartialEq)]
-enum Shape {
-    Rock,
-    Paper,
-    Scissors,
+/// A shape, identified by its position in the cycle of a [Game].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Shape(usize);
+
+impl Shape {
+    const ROCK: Shape = Shape(0);
+    const PAPER: Shape = Shape(1);
+    const SCISSORS: Shape = Shape(2);
+
+    fn score(self, game: &Game) -> u32 {
+        game.shape_scores[self.0]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct OutcomeScores {
+    loss: u32,
+    draw: u32,
+    win: u32,
 }
 
-impl Ord for Shape {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match (self, other) {
-            (Shape::Rock, Shape::Rock) => Ordering::Equal,
-            (Shape::Rock, Shape::Paper) => Ordering::Less,
-            (Shape::Rock, Shape::Scissors) => Ordering::Greater,
-            (Shape::Paper, Shape::Rock) => Ordering::Greater,
-            (Shape::Paper, Shape::Paper) => Ordering::Equal,
-            (Shape::Paper, Shape::Scissors) => Ordering::Less,
-            (Shape::Scissors, Shape::Rock) => Ordering::Less,
-            (Shape::Scissors, Shape::Paper) => Ordering::Greater,
-            (Shape::Scissors, Shape::Scissors) => Ordering::Equal,
+impl OutcomeScores {
+    fn score(self, outcome: Ordering) -> u32 {
+        match outcome {
+            Ordering::Less => self.loss,
+            Ordering::Equal => self.draw,
+            Ordering::Greater => self.win,
         }
     }
 }
 
-impl PartialOrd for Shape {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// A hand game over an odd number of shapes arranged in a cycle.
+///
+/// Each shape beats the (n - 1) / 2 shapes before it in the cycle and loses to the (n - 1) / 2
+/// shapes after it, so every pair of different shapes has a winner.
+#[derive(Debug)]
+struct Game {
+    names: Vec<String>,
+    shape_scores: Vec<u32>,
+    outcome_scores: OutcomeScores,
+}
+
+impl Game {
+    fn new(
+        names: Vec<String>,
+        shape_scores: Vec<u32>,
+        outcome_scores: OutcomeScores,
+    ) -> Result<Self> {
+        if names.len().is_multiple_of(2) {
+            return Err(anyhow!(
+                "Invalid shape count (must be odd): {}",
+                names.len()
+            ));
+        }
+        if names.len() != shape_scores.len() {
+            return Err(anyhow!(
+                "Shape count {} does not match score count {}",
+                names.len(),
+                shape_scores.len()
+            ));
+        }
+
+        Ok(Game {
+            names,
+            shape_scores,
+            outcome_scores,
+        })
+    }
+
+    fn rock_paper_scissors() -> Self {
+        Game {
+            names: ["Rock", "Paper", "Scissors"].map(String::from).to_vec(),
+            shape_scores: vec![1, 2, 3],
+            outcome_scores: OutcomeScores {
+                loss: 0,
+                draw: 3,
+                win: 6,
+            },
+        }
+    }
+
+    /// Gets the outcome for the counter shape, e.g. [Ordering::Greater] if it wins.
+    fn outcome(&self, counter: Shape, opponent: Shape) -> Ordering {
+        let n = self.names.len();
+
+        match (counter.0 + n - opponent.0) % n {
+            0 => Ordering::Equal,
+            distance if distance <= n / 2 => Ordering::Greater,
+            _ => Ordering::Less,
+        }
+    }
+
+    /// Finds a counter shape that gives the outcome against the opponent.
+    fn counter_for(&self, opponent: Shape, outcome: Ordering) -> Shape {
+        let n = self.names.len();
+
+        match outcome {
+            Ordering::Less => Shape((opponent.0 + n - 1) % n),
+            Ordering::Equal => opponent,
+            Ordering::Greater => Shape((opponent.0 + 1) % n),
+        }
+    }
+
+    fn shape(&self, name: &str) -> Result<Shape> {
+        self.names
+            .iter()
+            .position(|shape| shape.eq_ignore_ascii_case(name))
+            .map(Shape)
+            .ok_or(anyhow!("Invalid shape: {}", name))
     }
 }
 
-impl Shape {
-    fn score(self) -> u32 {
-        match self {
-            Shape::Rock => 1,
-            Shape::Paper => 2,
-            Shape::Scissors => 3,
+impl FromStr for Game {
+    type Err = anyhow::Error;
+
+    /// Parses a game definition, e.g.:
+    ///
+    /// ```text
+    /// shapes: Rock=1 Spock=5 Paper=2 Lizard=4 Scissors=3
+    /// outcomes: loss=0 draw=3 win=6
+    /// ```
+    ///
+    /// The order of the shapes defines the cycle. The outcomes line is optional.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut names = Vec::new();
+        let mut shape_scores = Vec::new();
+        let mut outcome_scores = Game::rock_paper_scissors().outcome_scores;
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+
+            let Some((key, values)) = line.split_once(":") else {
+                return Err(anyhow!("Cannot split line: {}", line));
+            };
+
+            for value in values.split_whitespace() {
+                let Some((name, score)) = value.split_once("=") else {
+                    return Err(anyhow!("Cannot split score: {}", value));
+                };
+                let score = score.parse::<u32>()?;
+
+                match (key.trim(), name) {
+                    ("shapes", name) => {
+                        names.push(String::from(name));
+                        shape_scores.push(score);
+                    }
+                    ("outcomes", "loss") => outcome_scores.loss = score,
+                    ("outcomes", "draw") => outcome_scores.draw = score,
+                    ("outcomes", "win") => outcome_scores.win = score,
+                    (key, name) => return Err(anyhow!("Invalid setting: {}: {}", key, name)),
+                }
+            }
         }
+
+        Game::new(names, shape_scores, outcome_scores)
+    }
+}
+
+/// One way to read the X/Y/Z column of a strategy guide.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Mapping {
+    /// X/Y/Z are the shapes to play.
+    Shapes([Shape; 3]),
+    /// X/Y/Z are the outcomes to aim for.
+    Outcomes([Ordering; 3]),
+}
+
+impl Mapping {
+    /// Every one-to-one mapping of X/Y/Z to rock-paper-scissors shapes or to outcomes.
+    fn all() -> Vec<Self> {
+        const PERMUTATIONS: [[usize; 3]; 6] = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        const OUTCOMES: [Ordering; 3] = [Ordering::Less, Ordering::Equal, Ordering::Greater];
+
+        let shapes = PERMUTATIONS.map(|permutation| Mapping::Shapes(permutation.map(Shape)));
+        let outcomes =
+            PERMUTATIONS.map(|permutation| Mapping::Outcomes(permutation.map(|i| OUTCOMES[i])));
+
+        shapes.into_iter().chain(outcomes).collect()
+    }
+
+    fn describe(&self, game: &Game) -> String {
+        let columns = match self {
+            Mapping::Shapes(shapes) => shapes.map(|shape| game.names[shape.0].as_str()),
+            Mapping::Outcomes(outcomes) => outcomes.map(|outcome| match outcome {
+                Ordering::Less => "lose",
+                Ordering::Equal => "draw",
+                Ordering::Greater => "win",
+            }),
+        };
+
+        format!("X={} Y={} Z={}", columns[0], columns[1], columns[2])
+    }
+}
+
+/// Scores the guide under every [Mapping], keeping only those that hit the target if given.
+fn solve_cipher(input: &str, target: Option<u32>) -> Result<Vec<(Mapping, u32)>> {
+    let game = Game::rock_paper_scissors();
+
+    Mapping::all()
+        .into_iter()
+        .map(|mapping| {
+            let score = input
+                .lines()
+                .map(|line| Ok(Round::from_str_mapping(line, &mapping, &game)?.score(&game)))
+                .sum::<Result<u32>>()?;
+
+            Ok((mapping, score))
+        })
+        .filter(|result| match (result, target) {
+            (Ok((_, score)), Some(target)) => *score == target,
+            _ => true,
+        })
+        .collect()
+}
+
+fn cipher(input: String, target: Option<u32>) -> Result<String> {
+    let game = Game::rock_paper_scissors();
+    let solutions = solve_cipher(&input, target)?;
+
+    if solutions.is_empty() {
+        return Err(anyhow!("No mapping scores {}", target.unwrap_or_default()));
     }
+
+    Ok(solutions
+        .into_iter()
+        .map(|(mapping, score)| format!("{}: {score}\n", mapping.describe(&game)))
+        .collect())
 }
 
 struct Round(Shape, Shape);
 
 impl Round {
-    fn score(self) -> u32 {
+    fn score(self, game: &Game) -> u32 {
         let Round(opponent, counter) = self;
 
-        match counter.cmp(&opponent) {
-            Ordering::Less => counter.score(),
-            Ordering::Equal => counter.score() + 3,
-            Ordering::Greater => counter.score() + 6,
-        }
+        counter.score(game) + game.outcome_scores.score(game.outcome(counter, opponent))
     }
 
     fn from_str_part_1(s: &str) -> Result<Self> {
+        Round::from_str_mapping(
+            s,
+            &Mapping::Shapes([Shape::ROCK, Shape::PAPER, Shape::SCISSORS]),
+            &Game::rock_paper_scissors(),
+        )
+    }
+
+    fn from_str_part_2(s: &str, game: &Game) -> Result<Self> {
+        Round::from_str_mapping(
+            s,
+            &Mapping::Outcomes([Ordering::Less, Ordering::Equal, Ordering::Greater]),
+            game,
+        )
+    }
+
+    /// Parses a round with the X/Y/Z column deciphered by a [Mapping].
+    fn from_str_mapping(s: &str, mapping: &Mapping, game: &Game) -> Result<Self> {
         let Some((opponent, counter)) = s.split_once(" ") else {
             return Err(anyhow!("Cannot split s: {}", s));
         };
 
-        let opponent = match opponent {
-            "A" => Shape::Rock,
-            "B" => Shape::Paper,
-            "C" => Shape::Scissors,
+        let opponent = Round::parse_opponent(opponent)?;
+
+        let column = match counter {
+            "X" => 0,
+            "Y" => 1,
+            "Z" => 2,
             x => return Err(anyhow!("Invalid shape: {}", x)),
         };
 
-        let counter = match counter {
-            "X" => Shape::Rock,
-            "Y" => Shape::Paper,
-            "Z" => Shape::Scissors,
-            x => return Err(anyhow!("Invalid shape: {}", x)),
+        let counter = match mapping {
+            Mapping::Shapes(shapes) => shapes[column],
+            Mapping::Outcomes(outcomes) => game.counter_for(opponent, outcomes[column]),
         };
 
         Ok(Round(opponent, counter))
     }
 
-    fn from_str_part_2(s: &str) -> Result<Self> {
+    fn parse_opponent(s: &str) -> Result<Shape> {
+        match s {
+            "A" => Ok(Shape::ROCK),
+            "B" => Ok(Shape::PAPER),
+            "C" => Ok(Shape::SCISSORS),
+            x => Err(anyhow!("Invalid shape: {}", x)),
+        }
+    }
+
+    fn from_str_names(s: &str, game: &Game) -> Result<Self> {
         let Some((opponent, counter)) = s.split_once(" ") else {
             return Err(anyhow!("Cannot split s: {}", s));
         };
 
-        let opponent = match opponent {
-            "A" => Shape::Rock,
-            "B" => Shape::Paper,
-            "C" => Shape::Scissors,
-            x => return Err(anyhow!("Invalid shape: {}", x)),
-        };
+        Ok(Round(game.shape(opponent)?, game.shape(counter.trim())?))
+    }
+}
 
-        let counter = match (counter, &opponent) {
-            ("X", Shape::Rock) => Shape::Scissors,
-            ("X", Shape::Paper) => Shape::Rock,
-            ("X", Shape::Scissors) => Shape::Paper,
-            ("Y", x) => *x,
-            ("Z", Shape::Rock) => Shape::Paper,
-            ("Z", Shape::Paper) => Shape::Scissors,
-            ("Z", Shape::Scissors) => Shape::Rock,
-            (x, _) => return Err(anyhow!("Invalid shape: {}", x)),
-        };
+/// Limits on the moves that [best_response] may choose.
+struct Constraints {
+    max_uses: Option<usize>,
+    no_repeat: bool,
+}
 
-        Ok(Round(opponent, counter))
+/// The most shapes whose uses [best_sequence] can count.
+const MAX_COUNTED_SHAPES: usize = 5;
+
+/// The most progress states [best_sequence] visits over all rounds before giving up.
+const MAX_STATES: usize = 5_000_000;
+
+/// What the moves so far mean for the moves still allowed.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+struct Progress {
+    last: Option<Shape>,
+    /// Uses left per shape, capped at the most uses the rounds left allow, as a higher limit can
+    /// no longer bind. Left at zero when uses are not limited, to keep the state space small.
+    uses_left: [u16; MAX_COUNTED_SHAPES],
+}
+
+/// Finds the highest scoring moves against a sequence of opponent shapes.
+///
+/// Without the no-repeat rule the order of the rounds does not matter, so a use limit alone is
+/// solved as an assignment of rounds to shapes by [best_assignment]. Anything else goes to
+/// [best_sequence].
+fn best_response(
+    opponents: &[Shape],
+    game: &Game,
+    constraints: &Constraints,
+) -> Result<Option<(u32, Vec<Shape>)>> {
+    match constraints.max_uses {
+        Some(max_uses) if !constraints.no_repeat => Ok(best_assignment(opponents, game, max_uses)),
+        _ => best_sequence(opponents, game, constraints),
+    }
+}
+
+/// Finds the best moves with dynamic programming over the rounds.
+///
+/// Goes round by round, keeping the best score for each reachable [Progress], so the work per
+/// round grows with the number of distinct progress states rather than with the move sequences.
+/// Only the move and previous state are kept for earlier rounds, to recover the moves at the end.
+///
+/// With both a use limit and the no-repeat rule there is a state for most splits of the rounds
+/// played between the shapes, unless the limit is at least half the rounds and so never binds.
+/// The search gives up after [MAX_STATES] states rather than run out of memory.
+fn best_sequence(
+    opponents: &[Shape],
+    game: &Game,
+    constraints: &Constraints,
+) -> Result<Option<(u32, Vec<Shape>)>> {
+    let n = game.names.len();
+    let rounds = opponents.len();
+    let mut uses_left = [0; MAX_COUNTED_SHAPES];
+    if let Some(max_uses) = constraints.max_uses {
+        if n > MAX_COUNTED_SHAPES {
+            return Err(anyhow!(
+                "Cannot limit uses of more than {} shapes",
+                MAX_COUNTED_SHAPES
+            ));
+        }
+        if rounds > u16::MAX as usize {
+            return Err(anyhow!(
+                "Cannot limit uses over more than {} rounds",
+                u16::MAX
+            ));
+        }
+
+        uses_left[..n].fill(max_uses.min(most_uses(rounds, constraints)) as u16);
+    }
+
+    let mut layer = vec![(
+        Progress {
+            last: None,
+            uses_left,
+        },
+        0,
+    )];
+    let mut states = 0;
+    // For each round, the move and the index of the previous progress for each progress.
+    let mut back_pointers: Vec<Vec<(Shape, usize)>> = Vec::with_capacity(rounds);
+
+    for (round, opponent) in opponents.iter().enumerate() {
+        let rounds_left = rounds - round - 1;
+        let cap = most_uses(rounds_left, constraints) as u16;
+        let mut indices = HashMap::new();
+        let mut next: Vec<(Progress, u32)> = Vec::new();
+        let mut pointers = Vec::new();
+
+        for (previous, (progress, score)) in layer.iter().enumerate() {
+            for counter in (0..n).map(Shape) {
+                if constraints.no_repeat && progress.last == Some(counter) {
+                    continue;
+                }
+
+                let mut uses_left = progress.uses_left;
+                if constraints.max_uses.is_some() {
+                    if uses_left[counter.0] == 0 {
+                        continue;
+                    }
+                    uses_left[counter.0] -= 1;
+                    uses_left
+                        .iter_mut()
+                        .for_each(|uses| *uses = (*uses).min(cap));
+
+                    // Not enough uses left to play the remaining rounds.
+                    if uses_left.iter().map(|&uses| uses as usize).sum::<usize>() < rounds_left {
+                        continue;
+                    }
+                }
+
+                let progress = Progress {
+                    last: Some(counter),
+                    uses_left,
+                };
+                let score = score + Round(*opponent, counter).score(game);
+
+                match indices.entry(progress) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(next.len());
+                        next.push((progress, score));
+                        pointers.push((counter, previous));
+                    }
+                    Entry::Occupied(entry) => {
+                        let index = *entry.get();
+                        if score > next[index].1 {
+                            next[index].1 = score;
+                            pointers[index] = (counter, previous);
+                        }
+                    }
+                }
+            }
+        }
+
+        states += next.len();
+        if states > MAX_STATES {
+            return Err(anyhow!(
+                "Cannot search more than {} states, reached in round {} of {}: try a use limit of \
+                 at least half the rounds, or fewer rounds",
+                MAX_STATES,
+                round + 1,
+                rounds
+            ));
+        }
+
+        layer = next;
+        back_pointers.push(pointers);
+    }
+
+    let Some((mut index, (_, score))) = layer
+        .into_iter()
+        .enumerate()
+        .max_by_key(|(_, (_, score))| *score)
+    else {
+        return Ok(None);
+    };
+
+    // Walk back through the rounds to recover the moves.
+    let mut moves = Vec::with_capacity(rounds);
+    for pointers in back_pointers.iter().rev() {
+        let (counter, previous) = pointers[index];
+        moves.push(counter);
+        index = previous;
+    }
+    moves.reverse();
+
+    Ok(Some((score, moves)))
+}
+
+/// Gets the most times one shape can be played over some rounds.
+fn most_uses(rounds: usize, constraints: &Constraints) -> usize {
+    if constraints.no_repeat {
+        rounds.div_ceil(2)
+    } else {
+        rounds
+    }
+}
+
+/// Finds the best moves when each shape may be used at most max_uses times, in any order.
+///
+/// This is a transportation problem: the rounds against each opponent shape are sent to the
+/// counter shapes, each taking at most max_uses rounds, at the highest total score. It is solved
+/// as a min-cost flow from the opponent shapes to the counter shapes.
+fn best_assignment(opponents: &[Shape], game: &Game, max_uses: usize) -> Option<(u32, Vec<Shape>)> {
+    let n = game.names.len();
+    let mut rounds_against = vec![Vec::new(); n];
+    for (round, opponent) in opponents.iter().enumerate() {
+        rounds_against[opponent.0].push(round);
+    }
+
+    // Nodes are the source, then the opponent shapes, then the counter shapes, then the sink.
+    let (source, sink) = (0, 2 * n + 1);
+    let mut network = Network::new(2 * n + 2);
+    let mut plays = vec![Vec::with_capacity(n); n];
+    for (opponent, rounds) in rounds_against.iter().enumerate() {
+        network.add_edge(source, 1 + opponent, rounds.len(), 0);
+        for counter in 0..n {
+            let score = Round(Shape(opponent), Shape(counter)).score(game);
+            plays[opponent].push(network.add_edge(
+                1 + opponent,
+                1 + n + counter,
+                rounds.len(),
+                -(score as i64),
+            ));
+        }
+    }
+    for counter in 0..n {
+        network.add_edge(1 + n + counter, sink, max_uses, 0);
+    }
+
+    if network.min_cost_flow(source, sink) < opponents.len() {
+        return None;
+    }
+
+    let mut moves = vec![Shape(0); opponents.len()];
+    for (rounds, plays) in rounds_against.iter().zip(&plays) {
+        let mut rounds = rounds.iter();
+        for (counter, play) in plays.iter().enumerate() {
+            for round in rounds.by_ref().take(network.flow(*play)) {
+                moves[*round] = Shape(counter);
+            }
+        }
+    }
+    let score = opponents
+        .iter()
+        .zip(&moves)
+        .map(|(opponent, counter)| Round(*opponent, *counter).score(game))
+        .sum();
+
+    Some((score, moves))
+}
+
+struct Edge {
+    to: usize,
+    capacity: usize,
+    cost: i64,
+}
+
+/// A flow network with a cost per unit of flow on each edge.
+struct Network {
+    /// Edges in pairs of an edge and its residual, so the residual of edge e is e ^ 1.
+    edges: Vec<Edge>,
+    adjacent: Vec<Vec<usize>>,
+}
+
+impl Network {
+    fn new(nodes: usize) -> Self {
+        Network {
+            edges: Vec::new(),
+            adjacent: vec![Vec::new(); nodes],
+        }
+    }
+
+    /// Adds an edge and returns its index.
+    fn add_edge(&mut self, from: usize, to: usize, capacity: usize, cost: i64) -> usize {
+        let edge = self.edges.len();
+
+        self.adjacent[from].push(edge);
+        self.edges.push(Edge { to, capacity, cost });
+        self.adjacent[to].push(edge + 1);
+        self.edges.push(Edge {
+            to: from,
+            capacity: 0,
+            cost: -cost,
+        });
+
+        edge
+    }
+
+    /// Gets the flow through an edge.
+    fn flow(&self, edge: usize) -> usize {
+        self.edges[edge ^ 1].capacity
     }
+
+    /// Sends as much flow as possible from the source to the sink at the lowest cost, and returns
+    /// the flow sent.
+    ///
+    /// Augments along the cheapest path until the sink is unreachable. Residual edges have
+    /// negative costs, so paths are found with Bellman-Ford.
+    fn min_cost_flow(&mut self, source: usize, sink: usize) -> usize {
+        let mut sent = 0;
+
+        loop {
+            let mut distances: Vec<Option<i64>> = vec![None; self.adjacent.len()];
+            let mut via = vec![None; self.adjacent.len()];
+            distances[source] = Some(0);
+
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for (node, edges) in self.adjacent.iter().enumerate() {
+                    let Some(distance) = distances[node] else {
+                        continue;
+                    };
+                    for &edge in edges {
+                        let Edge { to, capacity, cost } = self.edges[edge];
+                        if capacity > 0 && distances[to].is_none_or(|to| distance + cost < to) {
+                            distances[to] = Some(distance + cost);
+                            via[to] = Some(edge);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+
+            if distances[sink].is_none() {
+                return sent;
+            }
+
+            let mut amount = usize::MAX;
+            let mut node = sink;
+            while let Some(edge) = via[node] {
+                amount = amount.min(self.edges[edge].capacity);
+                node = self.edges[edge ^ 1].to;
+            }
+
+            let mut node = sink;
+            while let Some(edge) = via[node] {
+                self.edges[edge].capacity -= amount;
+                self.edges[edge ^ 1].capacity += amount;
+                node = self.edges[edge ^ 1].to;
+            }
+            sent += amount;
+        }
+    }
+}
+
+fn optimise(input: String, game: Option<PathBuf>, constraints: Constraints) -> Result<String> {
+    // A custom game names the opponent's shapes, rock-paper-scissors uses A/B/C.
+    let (game, opponents) = match game {
+        Some(path) => {
+            let game = fs::read_to_string(path)?.parse::<Game>()?;
+            let opponents = parse_opponents(&input, |name| game.shape(name))?;
+
+            (game, opponents)
+        }
+        None => (
+            Game::rock_paper_scissors(),
+            parse_opponents(&input, Round::parse_opponent)?,
+        ),
+    };
+
+    let (score, moves) = best_response(&opponents, &game, &constraints)?
+        .ok_or(anyhow!("No moves satisfy the constraints"))?;
+
+    let moves = moves
+        .into_iter()
+        .map(|shape| game.names[shape.0].as_str())
+        .collect::<Vec<_>>();
+
+    Ok(format!("Best score: {score}\nMoves: {}\n", moves.join(" ")))
+}
+
+/// Parses the opponent column, ignoring anything after the first shape on a line.
+fn parse_opponents(input: &str, parse: impl Fn(&str) -> Result<Shape>) -> Result<Vec<Shape>> {
+    input
+        .lines()
+        .map(|line| parse(line.split_whitespace().next().unwrap_or(line)))
+        .collect()
 }
 
 #[cfg(test)]
@@ -141,6 +787,11 @@ mod tests {
 A Y
 B X
 C Z
+";
+
+    const LIZARD_SPOCK: &str = r"
+shapes: Rock=1 Spock=5 Paper=2 Lizard=4 Scissors=3
+outcomes: loss=0 draw=3 win=6
 ";
 
     #[test]
@@ -156,4 +807,271 @@ C Z
 
         Ok(())
     }
+
+    #[test]
+    fn lizard_spock() -> Result<()> {
+        let game = LIZARD_SPOCK.parse::<Game>()?;
+        let beats = |counter: &str, opponent: &str| -> Result<bool> {
+            Ok(game.outcome(game.shape(counter)?, game.shape(opponent)?) == Ordering::Greater)
+        };
+
+        for (winner, loser) in [
+            ("Scissors", "Paper"),
+            ("Paper", "Rock"),
+            ("Rock", "Lizard"),
+            ("Lizard", "Spock"),
+            ("Spock", "Scissors"),
+            ("Scissors", "Lizard"),
+            ("Lizard", "Paper"),
+            ("Paper", "Spock"),
+            ("Spock", "Rock"),
+            ("Rock", "Scissors"),
+        ] {
+            assert!(beats(winner, loser)?);
+            assert!(!beats(loser, winner)?);
+        }
+
+        // Spock vaporizes rock for 5 + 6, then lizard eats paper for 4 + 6.
+        let round = Round::from_str_names("rock spock", &game)?;
+        assert_eq!(round.score(&game), 11);
+        let round = Round::from_str_names("paper lizard", &game)?;
+        assert_eq!(round.score(&game), 10);
+
+        Ok(())
+    }
+
+    #[test]
+    fn cipher_matches_parts() -> Result<()> {
+        let solutions = solve_cipher(EXAMPLE_INPUT.trim(), None)?;
+
+        assert_eq!(solutions.len(), 12);
+        assert!(solutions.contains(&(
+            Mapping::Shapes([Shape::ROCK, Shape::PAPER, Shape::SCISSORS]),
+            15
+        )));
+        assert!(solutions.contains(&(
+            Mapping::Outcomes([Ordering::Less, Ordering::Equal, Ordering::Greater]),
+            12
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cipher_target() -> Result<()> {
+        let solutions = solve_cipher(EXAMPLE_INPUT.trim(), Some(15))?;
+
+        assert!(!solutions.is_empty());
+        assert!(solutions.iter().all(|(_, score)| *score == 15));
+
+        Ok(())
+    }
+
+    #[test]
+    fn optimise_unconstrained() -> Result<()> {
+        let game = Game::rock_paper_scissors();
+        let constraints = Constraints {
+            max_uses: None,
+            no_repeat: false,
+        };
+
+        let (score, moves) = best_response(
+            &[Shape::ROCK, Shape::PAPER, Shape::SCISSORS],
+            &game,
+            &constraints,
+        )?
+        .unwrap();
+
+        assert_eq!(score, 24);
+        assert_eq!(moves, [Shape::PAPER, Shape::SCISSORS, Shape::ROCK]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn optimise_constrained() -> Result<()> {
+        let game = Game::rock_paper_scissors();
+        let opponents = [Shape::ROCK; 3];
+
+        let no_repeat = Constraints {
+            max_uses: None,
+            no_repeat: true,
+        };
+        let (score, moves) = best_response(&opponents, &game, &no_repeat)?.unwrap();
+        assert_eq!(score, 20);
+        assert_eq!(moves, [Shape::PAPER, Shape::ROCK, Shape::PAPER]);
+
+        let max_uses = Constraints {
+            max_uses: Some(1),
+            no_repeat: false,
+        };
+        let (score, _) = best_response(&opponents, &game, &max_uses)?.unwrap();
+        assert_eq!(score, 15);
+
+        let impossible = Constraints {
+            max_uses: Some(0),
+            no_repeat: false,
+        };
+        assert!(best_response(&opponents, &game, &impossible)?.is_none());
+
+        let both = Constraints {
+            max_uses: Some(2),
+            no_repeat: true,
+        };
+        let (score, moves) = best_response(&opponents, &game, &both)?.unwrap();
+        assert_eq!(score, 20);
+        assert_eq!(moves, [Shape::PAPER, Shape::ROCK, Shape::PAPER]);
+
+        Ok(())
+    }
+
+    fn generate_opponents(rounds: usize, seed: u64) -> Vec<Shape> {
+        // xorshift64, so the opponents are the same on every run.
+        let mut state = seed;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            Shape((state % 3) as usize)
+        };
+
+        (0..rounds).map(|_| random()).collect()
+    }
+
+    #[test]
+    fn assignment_matches_sequence() -> Result<()> {
+        let game = Game::rock_paper_scissors();
+
+        for seed in 1..20 {
+            let opponents = generate_opponents(12, seed);
+            for max_uses in 4..=6 {
+                let constraints = Constraints {
+                    max_uses: Some(max_uses),
+                    no_repeat: false,
+                };
+
+                let (score, moves) = best_assignment(&opponents, &game, max_uses).unwrap();
+                let (expected, _) = best_sequence(&opponents, &game, &constraints)?.unwrap();
+
+                assert_eq!(score, expected);
+                assert!(
+                    (0..3).all(|shape| moves.iter().filter(|m| m.0 == shape).count() <= max_uses)
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn optimise_real_scale() -> Result<()> {
+        let game = Game::rock_paper_scissors();
+        let constraints = Constraints {
+            max_uses: Some(1000),
+            no_repeat: false,
+        };
+
+        // Paper wins 1000 rounds, rock draws 1000 and scissors loses the other 500.
+        let (score, _) = best_response(&[Shape::ROCK; 2500], &game, &constraints)?.unwrap();
+        assert_eq!(score, 1000 * 8 + 1000 * 4 + 500 * 3);
+
+        let opponents = generate_opponents(2500, 7);
+        let (score, moves) = best_response(&opponents, &game, &constraints)?.unwrap();
+        assert_eq!(
+            score,
+            opponents
+                .iter()
+                .zip(&moves)
+                .map(|(opponent, counter)| Round(*opponent, *counter).score(&game))
+                .sum()
+        );
+
+        let no_repeat = Constraints {
+            max_uses: None,
+            no_repeat: true,
+        };
+        let (no_repeat_score, moves) = best_response(&opponents, &game, &no_repeat)?.unwrap();
+        assert!(moves.windows(2).all(|pair| pair[0] != pair[1]));
+
+        // Alternating paper and rock uses each 1250 times.
+        let both = Constraints {
+            max_uses: Some(1250),
+            no_repeat: true,
+        };
+        let (score, _) = best_response(&[Shape::ROCK; 2500], &game, &both)?.unwrap();
+        assert_eq!(score, 1250 * 8 + 1250 * 4);
+
+        let both = Constraints {
+            max_uses: Some(1249),
+            no_repeat: true,
+        };
+        let (score, moves) = best_response(&opponents, &game, &both)?.unwrap();
+        assert!(score <= no_repeat_score);
+        assert!(moves.windows(2).all(|pair| pair[0] != pair[1]));
+        assert!((0..3).all(|shape| moves.iter().filter(|m| m.0 == shape).count() <= 1249));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_matches_brute_force() -> Result<()> {
+        let game = Game::rock_paper_scissors();
+        let opponents = generate_opponents(8, 3);
+
+        for max_uses in 2..=5 {
+            let constraints = Constraints {
+                max_uses: Some(max_uses),
+                no_repeat: true,
+            };
+
+            let expected = (0..3usize.pow(8))
+                .map(|code| {
+                    (0..8)
+                        .map(|round| Shape(code / 3usize.pow(round) % 3))
+                        .collect::<Vec<_>>()
+                })
+                .filter(|moves| moves.windows(2).all(|pair| pair[0] != pair[1]))
+                .filter(|moves| {
+                    (0..3).all(|shape| moves.iter().filter(|m| m.0 == shape).count() <= max_uses)
+                })
+                .map(|moves| {
+                    opponents
+                        .iter()
+                        .zip(&moves)
+                        .map(|(opponent, counter)| Round(*opponent, *counter).score(&game))
+                        .sum::<u32>()
+                })
+                .max();
+
+            assert_eq!(
+                best_sequence(&opponents, &game, &constraints)?.map(|(score, _)| score),
+                expected
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn optimise_custom_game() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("day-2-game-{}", std::process::id()));
+        fs::write(&path, LIZARD_SPOCK)?;
+
+        let constraints = Constraints {
+            max_uses: Some(1),
+            no_repeat: false,
+        };
+        let report = optimise(String::from("rock\nspock"), Some(path.clone()), constraints);
+        fs::remove_file(path)?;
+
+        // Spock vaporizes rock for 5 + 6, then lizard poisons Spock for 4 + 6.
+        assert_eq!(report?, "Best score: 21\nMoves: Spock Lizard\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn even_shape_count() {
+        assert!("shapes: Rock=1 Paper=2".parse::<Game>().is_err());
+    }
 }
@@ -1,13 +1,36 @@
-use std::{collections::HashSet, hash::Hash};
+use std::ops::RangeInclusive;
 
 use anyhow::{Result, anyhow};
+use clap::Subcommand;
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, Task, get_task, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Sums the badge priorities of groups of any size.
+    Badges {
+        /// Number of rucksacks per group.
+        #[arg(short, long, default_value_t = 3)]
+        group_size: usize,
+    },
+    /// Plans the fewest item moves that leave no item type in both compartments.
+    Reorganise,
+}
 
 fn main() {
-    match get_part("inputs/day-3.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task::<Mode>("inputs/day-3.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(Mode::Badges { group_size }, input)) => {
+            println!("{:?}", sum_badges(&input, group_size))
+        }
+        Ok(Task::Mode(Mode::Reorganise, input)) => match reorganise(&input) {
+            Ok(report) => print!("{report}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
@@ -15,34 +38,51 @@ fn main() {
 fn part_1(input: String) -> Result<u32> {
     input
         .lines()
-        .map(split_line_into_halves)
-        .map(|pair| reduce_intersection(&pair))
-        .collect::<Result<Vec<_>>>()?
-        .into_iter()
-        .map(map_priority)
+        .enumerate()
+        .map(|(index, line)| {
+            reduce_intersection(&split_line_into_halves(line)?, index + 1..=index + 1)
+        })
         .sum()
 }
 
 fn part_2(input: String) -> Result<u32> {
+    sum_badges(&input, 3)
+}
+
+/// Sums the priorities of the one item carried by every rucksack in each group.
+fn sum_badges(input: &str, group_size: usize) -> Result<u32> {
+    if group_size == 0 {
+        return Err(anyhow!("Invalid group size: {}", group_size));
+    }
+
     input
         .lines()
-        .map(|line| HashSet::from_iter(line.bytes()))
-        .collect::<Vec<_>>()
-        .chunks(3)
-        .map(reduce_intersection)
+        .map(item_mask)
         .collect::<Result<Vec<_>>>()?
-        .into_iter()
-        .map(map_priority)
+        .chunks(group_size)
+        .enumerate()
+        .map(|(index, group)| {
+            let first_line = index * group_size + 1;
+            let lines = first_line..=first_line + group.len() - 1;
+
+            if group.len() < group_size {
+                return Err(anyhow!(
+                    "{}: incomplete group of {} rucksacks (expected {})",
+                    describe_lines(&lines),
+                    group.len(),
+                    group_size
+                ));
+            }
+
+            reduce_intersection(group, lines)
+        })
         .sum()
 }
 
-fn split_line_into_halves(line: &str) -> Vec<HashSet<u8>> {
+fn split_line_into_halves(line: &str) -> Result<[u64; 2]> {
     let (left, right) = line.split_at(line.len() / 2);
 
-    vec![
-        HashSet::from_iter(left.bytes()),
-        HashSet::from_iter(right.bytes()),
-    ]
+    Ok([item_mask(left)?, item_mask(right)?])
 }
 
 fn map_priority(item: u8) -> Result<u32> {
@@ -53,27 +93,313 @@ fn map_priority(item: u8) -> Result<u32> {
     }
 }
 
-/// Finds an item that intersects every [HashSet] in a list.
-fn reduce_intersection<T>(sets: &[HashSet<T>]) -> Result<T>
-where
-    T: Copy + Eq + Hash,
-{
-    let intersection = sets
-        .iter()
-        .cloned()
-        .reduce(|acc, set| acc.intersection(&set).copied().collect::<HashSet<_>>())
-        .ok_or(anyhow!("Cannot find intersection in a triplet"))?;
+/// Converts items into a bitmask with bit (priority - 1) set for each item type present.
+fn item_mask(items: &str) -> Result<u64> {
+    items
+        .bytes()
+        .try_fold(0, |mask, item| Ok(mask | 1 << (map_priority(item)? - 1)))
+}
+
+/// Converts a bitmask back into its items, in priority order.
+fn mask_items(mask: u64) -> Vec<char> {
+    (0..52)
+        .filter(|bit| mask & 1 << bit != 0)
+        .map(|bit| match bit {
+            0..26 => (b'a' + bit) as char,
+            _ => (b'A' + bit - 26) as char,
+        })
+        .collect()
+}
+
+fn describe_lines(lines: &RangeInclusive<usize>) -> String {
+    if lines.start() == lines.end() {
+        format!("Line {}", lines.start())
+    } else {
+        format!("Lines {}-{}", lines.start(), lines.end())
+    }
+}
+
+/// Finds the priority of the one item that is in every bitmask in a list.
+///
+/// The lines are only used to say where the bitmasks came from if there is not exactly one.
+fn reduce_intersection(masks: &[u64], lines: RangeInclusive<usize>) -> Result<u32> {
+    let Some(intersection) = masks.iter().copied().reduce(|acc, mask| acc & mask) else {
+        return Err(anyhow!("Cannot find intersection in an empty list"));
+    };
+
+    match intersection.count_ones() {
+        1 => Ok(intersection.trailing_zeros() + 1),
+        0 => Err(anyhow!(
+            "{}: expected exactly one common item, found none",
+            describe_lines(&lines)
+        )),
+        count => Err(anyhow!(
+            "{}: expected exactly one common item, found {}: {}",
+            describe_lines(&lines),
+            count,
+            mask_items(intersection)
+                .iter()
+                .map(char::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Moves some items of one type to the other compartment.
+#[derive(Debug, Eq, PartialEq)]
+struct Move {
+    item: char,
+    count: usize,
+    to_left: bool,
+}
 
-    Vec::from_iter(intersection)
-        .first()
-        .copied()
-        .ok_or(anyhow!("Cannot find first element of intersection"))
+#[derive(Debug, Eq, PartialEq)]
+enum Plan {
+    Moves(Vec<Move>),
+    /// No set of whole item types fills exactly half the rucksack.
+    Impossible {
+        totals: Vec<(char, usize)>,
+        half: usize,
+    },
+}
+
+/// Plans the fewest moves after which every item type is in one compartment only, with both
+/// compartments still the same size.
+///
+/// Picking the item types that end up on the left decides everything: their items on the right
+/// move left, and every other type's items on the left move right. The types on the left must
+/// fill exactly half the rucksack, and the moves in each direction then balance out, so this is
+/// a subset sum over the item type totals that minimises the items moved left.
+fn plan_reorganisation(line: &str) -> Result<Plan> {
+    if !line.len().is_multiple_of(2) {
+        return Err(anyhow!(
+            "Cannot split rucksack of odd length into equal compartments: {}",
+            line
+        ));
+    }
+
+    let half = line.len() / 2;
+    let (left, right) = line.split_at(half);
+
+    let mut counts = [(0, 0); 52];
+    for item in left.bytes() {
+        counts[map_priority(item)? as usize - 1].0 += 1;
+    }
+    for item in right.bytes() {
+        counts[map_priority(item)? as usize - 1].1 += 1;
+    }
+    let types = (0..52u64)
+        .filter(|bit| counts[*bit as usize] != (0, 0))
+        .map(|bit| (mask_items(1 << bit)[0], counts[bit as usize]))
+        .collect::<Vec<_>>();
+
+    // costs[k][size]: fewest items moved left to fill the left compartment to size using only
+    // the first k types, or None if that size cannot be reached.
+    let mut costs = vec![vec![None; half + 1]; types.len() + 1];
+    costs[0][0] = Some(0);
+    for (k, (_, (on_left, on_right))) in types.iter().enumerate() {
+        for size in 0..=half {
+            let skip = costs[k][size];
+            let take = (size >= on_left + on_right)
+                .then(|| costs[k][size - on_left - on_right].map(|cost| cost + on_right))
+                .flatten();
+
+            costs[k + 1][size] = match (skip, take) {
+                (Some(skip), Some(take)) => Some(skip.min(take)),
+                (skip, take) => skip.or(take),
+            };
+        }
+    }
+
+    if costs[types.len()][half].is_none() {
+        return Ok(Plan::Impossible {
+            totals: types
+                .iter()
+                .map(|(item, (on_left, on_right))| (*item, on_left + on_right))
+                .collect(),
+            half,
+        });
+    }
+
+    // Walk back through the types to see which ones went left.
+    let mut moves = Vec::new();
+    let mut size = half;
+    for (k, (item, (on_left, on_right))) in types.iter().enumerate().rev() {
+        let stays_right = costs[k][size] == costs[k + 1][size];
+
+        if stays_right {
+            if *on_left > 0 {
+                moves.push(Move {
+                    item: *item,
+                    count: *on_left,
+                    to_left: false,
+                });
+            }
+        } else {
+            if *on_right > 0 {
+                moves.push(Move {
+                    item: *item,
+                    count: *on_right,
+                    to_left: true,
+                });
+            }
+            size -= on_left + on_right;
+        }
+    }
+    moves.reverse();
+
+    Ok(Plan::Moves(moves))
+}
+
+fn reorganise(input: &str) -> Result<String> {
+    let mut report = String::new();
+    let mut total = 0;
+
+    for (index, line) in input.lines().enumerate() {
+        let description = match plan_reorganisation(line)? {
+            Plan::Moves(moves) if moves.is_empty() => String::from("already sorted"),
+            Plan::Moves(moves) => {
+                total += moves.iter().map(|step| step.count).sum::<usize>();
+
+                moves
+                    .iter()
+                    .map(|step| {
+                        let direction = if step.to_left { "to left" } else { "to right" };
+                        format!("{} {} {direction}", step.count, step.item)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+            Plan::Impossible { totals, half } => format!(
+                "impossible, no subset of the item type totals {} adds up to {half}",
+                totals
+                    .iter()
+                    .map(|(item, count)| format!("{item}={count}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+        };
+
+        report += &format!(
+            "{}: {description}\n",
+            describe_lines(&(index + 1..=index + 1))
+        );
+    }
+    report += &format!("Total moves: {total}\n");
+
+    Ok(report)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Instant;
+
     use super::*;
 
+    /// The original solution, which intersects a [HashSet] of items per compartment or rucksack.
+    mod hash_set {
+        use std::{collections::HashSet, hash::Hash};
+
+        use super::*;
+
+        pub fn part_1(input: &str) -> Result<u32> {
+            input
+                .lines()
+                .map(split_line_into_halves)
+                .map(|pair| reduce_intersection(&pair))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .map(map_priority)
+                .sum()
+        }
+
+        pub fn part_2(input: &str) -> Result<u32> {
+            input
+                .lines()
+                .map(|line| HashSet::from_iter(line.bytes()))
+                .collect::<Vec<_>>()
+                .chunks(3)
+                .map(reduce_intersection)
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .map(map_priority)
+                .sum()
+        }
+
+        fn split_line_into_halves(line: &str) -> Vec<HashSet<u8>> {
+            let (left, right) = line.split_at(line.len() / 2);
+
+            vec![
+                HashSet::from_iter(left.bytes()),
+                HashSet::from_iter(right.bytes()),
+            ]
+        }
+
+        /// Finds an item that intersects every [HashSet] in a list.
+        fn reduce_intersection<T>(sets: &[HashSet<T>]) -> Result<T>
+        where
+            T: Copy + Eq + Hash,
+        {
+            let intersection = sets
+                .iter()
+                .cloned()
+                .reduce(|acc, set| acc.intersection(&set).copied().collect::<HashSet<_>>())
+                .ok_or(anyhow!("Cannot find intersection in a triplet"))?;
+
+            Vec::from_iter(intersection)
+                .first()
+                .copied()
+                .ok_or(anyhow!("Cannot find first element of intersection"))
+        }
+    }
+
+    /// Generates valid rucksacks: each shares one item between its halves, and each group of three
+    /// shares exactly one badge.
+    fn generate_rucksacks(rucksacks: usize) -> String {
+        const ITEMS: &[u8; 52] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+        const HALF_LEN: usize = 12;
+
+        // xorshift64, so the input is the same on every run.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+
+        let mut lines = Vec::new();
+        for _ in 0..rucksacks.div_ceil(3) {
+            // Shuffle the items, then take the badge and a disjoint pool of 17 items per rucksack.
+            let mut items = *ITEMS;
+            for i in (1..items.len()).rev() {
+                items.swap(i, random(i + 1));
+            }
+            let (badge, pools) = (items[0], &items[1..]);
+
+            for pool in pools.chunks(17) {
+                let (shared, rest) = (pool[0], &pool[1..]);
+                let (left_pool, right_pool) = rest.split_at(rest.len() / 2);
+
+                let mut left = vec![shared, badge];
+                left.extend((2..HALF_LEN).map(|_| left_pool[random(left_pool.len())]));
+                let mut right = vec![shared];
+                right.extend((1..HALF_LEN).map(|_| right_pool[random(right_pool.len())]));
+
+                for half in [&mut left, &mut right] {
+                    for i in (1..half.len()).rev() {
+                        half.swap(i, random(i + 1));
+                    }
+                }
+
+                lines.push(String::from_utf8_lossy(&[left, right].concat()).to_string());
+            }
+        }
+
+        lines.join("\n")
+    }
+
     const EXAMPLE_INPUT: &str = r"
 vJrwpWtwJgWrhcsFMMfFFhFp
 jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
@@ -96,4 +422,127 @@ CrZsJsPPZsGzwwsLwLmpwMDw
 
         Ok(())
     }
+
+    #[test]
+    fn matches_hash_set() -> Result<()> {
+        let input = generate_rucksacks(300);
+
+        assert_eq!(part_1(input.clone())?, hash_set::part_1(&input)?);
+        assert_eq!(part_2(input.clone())?, hash_set::part_2(&input)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn group_size() -> Result<()> {
+        assert_eq!(sum_badges(EXAMPLE_INPUT.trim(), 3)?, 70);
+        assert_eq!(sum_badges("ab\ncb\nbd\nbe", 2)?, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn group_diagnostics() {
+        let error = sum_badges(EXAMPLE_INPUT.trim(), 2).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Lines 1-2: expected exactly one common item, found 5: f, r, s, F, M"
+        );
+
+        let error = sum_badges(EXAMPLE_INPUT.trim(), 4).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Lines 1-4: expected exactly one common item, found none"
+        );
+
+        let error = sum_badges("ab\ncb\nbd\nbe", 3).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Line 4: incomplete group of 1 rucksacks (expected 3)"
+        );
+    }
+
+    /// Applies a plan and checks that it sorts the rucksack without unbalancing it.
+    fn check_plan(line: &str) -> Result<usize> {
+        let Plan::Moves(moves) = plan_reorganisation(line)? else {
+            return Err(anyhow!("No plan for {}", line));
+        };
+
+        let (left, right) = line.split_at(line.len() / 2);
+        let (mut left, mut right) = (left.to_string(), right.to_string());
+        for step in &moves {
+            let (from, to) = if step.to_left {
+                (&mut right, &mut left)
+            } else {
+                (&mut left, &mut right)
+            };
+            for _ in 0..step.count {
+                from.remove(from.find(step.item).unwrap());
+                to.push(step.item);
+            }
+        }
+
+        assert_eq!(left.len(), right.len());
+        assert_eq!(item_mask(&left)? & item_mask(&right)?, 0);
+
+        Ok(moves.iter().map(|step| step.count).sum())
+    }
+
+    #[test]
+    fn reorganise_example() -> Result<()> {
+        for line in EXAMPLE_INPUT.trim().lines() {
+            check_plan(line)?;
+        }
+
+        assert_eq!(check_plan("abba")?, 2);
+        assert_eq!(check_plan("abcd")?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reorganise_impossible() -> Result<()> {
+        assert_eq!(
+            plan_reorganisation("aaab")?,
+            Plan::Impossible {
+                totals: vec![('a', 3), ('b', 1)],
+                half: 2
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reorganise_odd_length() {
+        assert!(plan_reorganisation("aab").is_err());
+    }
+
+    #[test]
+    fn invalid_item() {
+        assert!(part_1(String::from("ab1b")).is_err());
+    }
+
+    /// Run with `cargo test --release -p day-3 -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_against_hash_set() -> Result<()> {
+        let input = generate_rucksacks(300_000);
+
+        let start = Instant::now();
+        let bitmask = (part_1(input.clone())?, part_2(input.clone())?);
+        let bitmask_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        let hash_set = (hash_set::part_1(&input)?, hash_set::part_2(&input)?);
+        let hash_set_elapsed = start.elapsed();
+
+        println!(
+            "bitmask {bitmask_elapsed:.2?}, HashSet {hash_set_elapsed:.2?} ({:.1}x)",
+            hash_set_elapsed.as_secs_f64() / bitmask_elapsed.as_secs_f64()
+        );
+        assert_eq!(bitmask, hash_set);
+
+        Ok(())
+    }
 }
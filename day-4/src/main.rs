@@ -1,57 +1,280 @@
-use std::ops::RangeInclusive;
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Write, ops::RangeInclusive};
 
 use anyhow::{Result, anyhow};
+use clap::Subcommand;
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, Task, get_task, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Reports overlapping groups, containment chains and uncovered sections across all lines.
+    Graph,
+}
 
 fn main() {
-    match get_part("inputs/day-4.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task::<Mode>("inputs/day-4.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(Mode::Graph, input)) => match graph(&input) {
+            Ok(report) => print!("{report}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
+/// Counts the lines where one assignment fully contains another.
 fn part_1(input: String) -> Result<usize> {
-    Ok(input
-        .lines()
-        .map(parse_line_into_ranges)
-        .collect::<Result<Vec<_>>>()?
-        .into_iter()
-        .filter(|(first, second)| {
-            (first.contains(second.start()) && first.contains(second.end()))
-                || (second.contains(first.start()) && second.contains(first.end()))
-        })
-        .count())
+    count_lines_with_pair(&input, contains)
 }
 
+/// Counts the lines where two assignments overlap.
 fn part_2(input: String) -> Result<usize> {
+    count_lines_with_pair(&input, overlaps)
+}
+
+fn count_lines_with_pair(
+    input: &str,
+    predicate: fn(&RangeInclusive<u32>, &RangeInclusive<u32>) -> bool,
+) -> Result<usize> {
     Ok(input
         .lines()
         .map(parse_line_into_ranges)
         .collect::<Result<Vec<_>>>()?
         .into_iter()
-        .filter(|(first, second)| first.contains(second.start()) || second.contains(first.start()))
+        .filter(|ranges| {
+            ranges.iter().enumerate().any(|(index, first)| {
+                ranges[index + 1..]
+                    .iter()
+                    .any(|second| predicate(first, second) || predicate(second, first))
+            })
+        })
         .count())
 }
 
-fn parse_line_into_ranges(line: &str) -> Result<(RangeInclusive<u8>, RangeInclusive<u8>)> {
-    let Some((first, second)) = line.split_once(",") else {
-        return Err(anyhow!("Cannot split line into ranges: {}", line));
-    };
+fn contains(outer: &RangeInclusive<u32>, inner: &RangeInclusive<u32>) -> bool {
+    outer.start() <= inner.start() && inner.end() <= outer.end()
+}
+
+fn overlaps(first: &RangeInclusive<u32>, second: &RangeInclusive<u32>) -> bool {
+    first.start() <= second.end() && second.start() <= first.end()
+}
+
+fn parse_line_into_ranges(line: &str) -> Result<Vec<RangeInclusive<u32>>> {
+    line.split(",").map(parse_range).collect()
+}
 
-    let Some((first_left, first_right)) = first.split_once("-") else {
-        return Err(anyhow!("Cannot split range into boundaries: {}", first));
+fn parse_range(range: &str) -> Result<RangeInclusive<u32>> {
+    let Some((left, right)) = range.split_once("-") else {
+        return Err(anyhow!("Cannot split range into boundaries: {}", range));
     };
 
-    let Some((second_left, second_right)) = second.split_once("-") else {
-        return Err(anyhow!("Cannot split range into boundaries: {}", second));
+    let (start, end) = (left.parse()?, right.parse()?);
+    if start > end {
+        return Err(anyhow!(
+            "Cannot parse range ending before it starts: {}",
+            range
+        ));
+    }
+
+    Ok(start..=end)
+}
+
+/// An assignment of one elf, numbered from 1 by line and by position on the line.
+struct Assignment {
+    line: usize,
+    elf: usize,
+    sections: RangeInclusive<u32>,
+}
+
+impl Assignment {
+    fn describe(&self) -> String {
+        format!(
+            "{}.{} ({}-{})",
+            self.line,
+            self.elf,
+            self.sections.start(),
+            self.sections.end()
+        )
+    }
+}
+
+/// A connected component of the overlap graph, with the sections its assignments cover.
+struct Group {
+    sections: RangeInclusive<u32>,
+    members: Vec<usize>,
+}
+
+fn parse_assignments(input: &str) -> Result<Vec<Assignment>> {
+    let mut assignments = Vec::new();
+
+    for (line_index, line) in input.lines().enumerate() {
+        for (elf_index, sections) in parse_line_into_ranges(line)?.into_iter().enumerate() {
+            assignments.push(Assignment {
+                line: line_index + 1,
+                elf: elf_index + 1,
+                sections,
+            });
+        }
+    }
+
+    Ok(assignments)
+}
+
+/// Sorts assignment indices by start, with longer assignments first on equal starts, so that
+/// every assignment comes after the ones containing it.
+fn sorted_by_start(assignments: &[Assignment]) -> Vec<usize> {
+    let mut order = (0..assignments.len()).collect::<Vec<_>>();
+    order.sort_by_key(|index| {
+        let sections = &assignments[*index].sections;
+        (*sections.start(), Reverse(*sections.end()))
+    });
+
+    order
+}
+
+/// Counts the edges of the overlap graph.
+///
+/// Sweeping by start, an assignment overlaps exactly those earlier ones that have not ended yet.
+fn count_overlapping_pairs(assignments: &[Assignment]) -> usize {
+    let mut ends = BinaryHeap::new();
+    let mut pairs = 0;
+
+    for index in sorted_by_start(assignments) {
+        let sections = &assignments[index].sections;
+        while ends
+            .peek()
+            .is_some_and(|Reverse(end)| end < sections.start())
+        {
+            ends.pop();
+        }
+
+        pairs += ends.len();
+        ends.push(Reverse(*sections.end()));
+    }
+
+    pairs
+}
+
+/// Splits the overlap graph into connected components.
+///
+/// Intervals that overlap are connected, so a component is a run of assignments sorted by start
+/// where each one starts before the run so far has ended.
+fn find_groups(assignments: &[Assignment]) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+
+    for index in sorted_by_start(assignments) {
+        let sections = &assignments[index].sections;
+
+        if let Some(group) = groups.last_mut()
+            && sections.start() <= group.sections.end()
+        {
+            group.sections = *group.sections.start()..=*group.sections.end().max(sections.end());
+            group.members.push(index);
+        } else {
+            groups.push(Group {
+                sections: sections.clone(),
+                members: vec![index],
+            });
+        }
+    }
+
+    groups
+}
+
+/// Finds the longest chain of assignments in a group where each one contains the next.
+fn longest_containment_chain(assignments: &[Assignment], group: &Group) -> Vec<usize> {
+    // The members are sorted by start, so a containing assignment always comes first.
+    let members = &group.members;
+    let mut lengths = vec![1; members.len()];
+    let mut previous = vec![None; members.len()];
+
+    for inner in 0..members.len() {
+        for outer in 0..inner {
+            if lengths[outer] + 1 > lengths[inner]
+                && contains(
+                    &assignments[members[outer]].sections,
+                    &assignments[members[inner]].sections,
+                )
+            {
+                lengths[inner] = lengths[outer] + 1;
+                previous[inner] = Some(outer);
+            }
+        }
+    }
+
+    let Some(mut last) = (0..members.len()).max_by_key(|index| (lengths[*index], Reverse(*index)))
+    else {
+        return Vec::new();
     };
 
-    Ok((
-        (first_left.parse()?..=first_right.parse()?),
-        (second_left.parse()?..=second_right.parse()?),
-    ))
+    let mut chain = vec![members[last]];
+    while let Some(outer) = previous[last] {
+        chain.push(members[outer]);
+        last = outer;
+    }
+    chain.reverse();
+
+    chain
+}
+
+/// Finds the sections between the first and the last assigned one that no elf covers.
+fn find_uncovered(groups: &[Group]) -> Vec<RangeInclusive<u32>> {
+    groups
+        .windows(2)
+        .filter(|pair| *pair[0].sections.end() + 1 < *pair[1].sections.start())
+        .map(|pair| *pair[0].sections.end() + 1..=*pair[1].sections.start() - 1)
+        .collect()
+}
+
+fn graph(input: &str) -> Result<String> {
+    let assignments = parse_assignments(input)?;
+    let groups = find_groups(&assignments);
+    let mut report = String::new();
+
+    writeln!(
+        report,
+        "Assignments: {} on {} lines",
+        assignments.len(),
+        input.lines().count()
+    )?;
+    writeln!(
+        report,
+        "Overlapping pairs: {}",
+        count_overlapping_pairs(&assignments)
+    )?;
+
+    writeln!(report, "Groups: {}", groups.len())?;
+    for group in &groups {
+        let chain = longest_containment_chain(&assignments, group)
+            .into_iter()
+            .map(|index| assignments[index].describe())
+            .collect::<Vec<_>>();
+
+        writeln!(
+            report,
+            "  {}-{}: {} assignments, longest containment chain {}",
+            group.sections.start(),
+            group.sections.end(),
+            group.members.len(),
+            chain.join(" > ")
+        )?;
+    }
+
+    let uncovered = find_uncovered(&groups)
+        .iter()
+        .map(|sections| format!("{}-{}", sections.start(), sections.end()))
+        .collect::<Vec<_>>();
+    if uncovered.is_empty() {
+        writeln!(report, "Uncovered sections: none")?;
+    } else {
+        writeln!(report, "Uncovered sections: {}", uncovered.join(", "))?;
+    }
+
+    Ok(report)
 }
 
 #[cfg(test)]
@@ -80,4 +303,45 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn many_assignments() -> Result<()> {
+        let input =
+            String::from("1-2,5-9,6-7\n1-3,4-6,7-9\n4000000000-4000000005,4000000001-4000000009");
+
+        assert_eq!(part_1(input.clone())?, 1);
+        assert_eq!(part_2(input)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn overlap_graph() -> Result<()> {
+        let assignments = parse_assignments("2-8,3-7\n4-6,20-30\n21-21,2-3,10-12")?;
+        let groups = find_groups(&assignments);
+
+        assert_eq!(count_overlapping_pairs(&assignments), 6);
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| (group.sections.clone(), group.members.len()))
+                .collect::<Vec<_>>(),
+            [(2..=8, 4), (10..=12, 1), (20..=30, 2)]
+        );
+        assert_eq!(
+            longest_containment_chain(&assignments, &groups[0])
+                .iter()
+                .map(|index| assignments[*index].describe())
+                .collect::<Vec<_>>(),
+            ["1.1 (2-8)", "1.2 (3-7)", "2.1 (4-6)"]
+        );
+        assert_eq!(find_uncovered(&groups), [9..=9, 13..=19]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reversed_range() {
+        assert!(parse_line_into_ranges("5-3,1-2").is_err());
+    }
 }
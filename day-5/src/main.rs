@@ -1,12 +1,69 @@
+use std::fmt::Write;
+
 use anyhow::{Result, anyhow};
+use clap::{Args, Subcommand, ValueEnum};
 use regex::Regex;
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, Task, get_task, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Rearranges the stacks with any crane model and prints the top crates.
+    Simulate {
+        #[command(flatten)]
+        crane: CraneArgs,
+    },
+    /// Prints the stacks drawing after every step of the procedure.
+    Replay {
+        #[command(flatten)]
+        crane: CraneArgs,
+
+        /// Only prints the drawing after this step. Step 0 is the starting drawing.
+        #[arg(short, long)]
+        step: Option<usize>,
+    },
+}
+
+#[derive(Args)]
+struct CraneArgs {
+    #[arg(short, long, default_value = "9001")]
+    crane: CraneModel,
+
+    /// Most crates the limited and flipping cranes can lift at once.
+    #[arg(short = 'k', long)]
+    max_lift: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CraneModel {
+    /// Moves one crate at a time.
+    #[value(name = "9000")]
+    CrateMover9000,
+    /// Moves all crates at once.
+    #[value(name = "9001")]
+    CrateMover9001,
+    /// Moves up to K crates at once, keeping their order.
+    Limited,
+    /// Moves up to K crates at once, putting each lift down upside down.
+    Flipping,
+}
 
 fn main() {
-    match get_part("inputs/day-5.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task::<Mode>("inputs/day-5.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(Mode::Simulate { crane }, input)) => {
+            println!("{:?}", simulate(input, crane.crane, crane.max_lift))
+        }
+        Ok(Task::Mode(Mode::Replay { crane, step }, input)) => {
+            match replay(input, crane.crane, crane.max_lift, step) {
+                Ok(report) => print!("{report}"),
+                Err(error) => println!("{error:?}"),
+            }
+        }
         Err(error) => println!("{error:?}"),
     }
 }
@@ -14,9 +71,89 @@ fn main() {
 fn part_1(input: String) -> Result<String> {
     let (mut stacks, steps) = convert_input_into_stacks_steps(input)?;
 
-    for step in steps {
-        let (from, to, size) = step;
+    rearrange(&mut stacks, &steps, &CrateMover9000)?;
+
+    get_tops(stacks)
+}
+
+fn part_2(input: String) -> Result<String> {
+    let (mut stacks, steps) = convert_input_into_stacks_steps(input)?;
+
+    rearrange(&mut stacks, &steps, &CrateMover9001)?;
+
+    get_tops(stacks)
+}
+
+fn simulate(input: String, model: CraneModel, max_lift: Option<usize>) -> Result<String> {
+    let crane = build_crane(model, max_lift)?;
+    let (mut stacks, steps) = convert_input_into_stacks_steps(input)?;
+
+    rearrange(&mut stacks, &steps, crane.as_ref())?;
+
+    get_tops(stacks)
+}
+
+fn replay(
+    input: String,
+    model: CraneModel,
+    max_lift: Option<usize>,
+    step: Option<usize>,
+) -> Result<String> {
+    let crane = build_crane(model, max_lift)?;
+    let (mut stacks, steps) = convert_input_into_stacks_steps(input)?;
+    if let Some(step) = step
+        && step > steps.len()
+    {
+        return Err(anyhow!(
+            "Invalid step: {} (the procedure has {} steps)",
+            step,
+            steps.len()
+        ));
+    }
+
+    let mut report = String::new();
+    if step.is_none_or(|step| step == 0) {
+        writeln!(report, "Start\n{}\n", render_stacks(&stacks))?;
+    }
+
+    for (index, (from, to, size)) in steps.iter().enumerate() {
+        crane.move_crates(&mut stacks, *from, *to, *size)?;
+
+        if step.is_none_or(|step| step == index + 1) {
+            writeln!(
+                report,
+                "After step {}: move {size} from {} to {}\n{}\n",
+                index + 1,
+                from + 1,
+                to + 1,
+                render_stacks(&stacks)
+            )?;
+        }
+    }
 
+    Ok(report)
+}
+
+/// A crane that carries out one step of the rearrangement procedure.
+trait Crane {
+    /// Moves size crates from the top of stack from to the top of stack to.
+    fn move_crates(&self, stacks: &mut Stacks, from: usize, to: usize, size: usize) -> Result<()>;
+}
+
+struct CrateMover9000;
+
+struct CrateMover9001;
+
+struct LimitedCrane {
+    max_lift: usize,
+}
+
+struct FlippingCrane {
+    max_lift: usize,
+}
+
+impl Crane for CrateMover9000 {
+    fn move_crates(&self, stacks: &mut Stacks, from: usize, to: usize, size: usize) -> Result<()> {
         for _ in 0..size {
             let Some(top) = stacks[from].pop() else {
                 return Err(anyhow!("Cannot pop from stack: {}", from));
@@ -24,34 +161,85 @@ fn part_1(input: String) -> Result<String> {
 
             stacks[to].push(top);
         }
+
+        Ok(())
     }
+}
 
-    let tops = stacks
-        .into_iter()
-        .map(|stack| {
-            stack
-                .into_iter()
-                .next_back()
-                .ok_or(anyhow!("Cannot get top"))
-        })
-        .collect::<Result<Vec<_>>>()?;
+impl Crane for CrateMover9001 {
+    fn move_crates(&self, stacks: &mut Stacks, from: usize, to: usize, size: usize) -> Result<()> {
+        let crates = lift(stacks, from, size)?;
+        stacks[to].extend_from_slice(&crates);
 
-    Ok(String::from_utf8(tops)?)
+        Ok(())
+    }
 }
 
-fn part_2(input: String) -> Result<String> {
-    let (mut stacks, steps) = convert_input_into_stacks_steps(input)?;
+impl Crane for LimitedCrane {
+    fn move_crates(&self, stacks: &mut Stacks, from: usize, to: usize, size: usize) -> Result<()> {
+        let mut remaining = size;
+        while remaining > 0 {
+            let crates = lift(stacks, from, remaining.min(self.max_lift))?;
+            remaining -= crates.len();
+
+            stacks[to].extend_from_slice(&crates);
+        }
 
-    for step in steps {
-        let (from, to, size) = step;
+        Ok(())
+    }
+}
 
-        let to_keep = stacks[from].len() - size;
-        let to_move = Vec::from(&stacks[from][to_keep..]);
+impl Crane for FlippingCrane {
+    fn move_crates(&self, stacks: &mut Stacks, from: usize, to: usize, size: usize) -> Result<()> {
+        let mut remaining = size;
+        while remaining > 0 {
+            let crates = lift(stacks, from, remaining.min(self.max_lift))?;
+            remaining -= crates.len();
 
-        stacks[to].extend_from_slice(&to_move);
-        stacks[from].truncate(to_keep);
+            stacks[to].extend(crates.into_iter().rev());
+        }
+
+        Ok(())
+    }
+}
+
+/// Takes the top size crates off a stack, bottom first.
+fn lift(stacks: &mut Stacks, from: usize, size: usize) -> Result<Vec<u8>> {
+    let Some(to_keep) = stacks[from].len().checked_sub(size) else {
+        return Err(anyhow!("Cannot lift {} crates from stack: {}", size, from));
+    };
+
+    Ok(stacks[from].split_off(to_keep))
+}
+
+fn build_crane(model: CraneModel, max_lift: Option<usize>) -> Result<Box<dyn Crane>> {
+    let max_lift = || match max_lift {
+        Some(0) => Err(anyhow!("Invalid max lift: 0")),
+        Some(max_lift) => Ok(max_lift),
+        None => Err(anyhow!("Missing max lift for crane")),
+    };
+
+    Ok(match model {
+        CraneModel::CrateMover9000 => Box::new(CrateMover9000),
+        CraneModel::CrateMover9001 => Box::new(CrateMover9001),
+        CraneModel::Limited => Box::new(LimitedCrane {
+            max_lift: max_lift()?,
+        }),
+        CraneModel::Flipping => Box::new(FlippingCrane {
+            max_lift: max_lift()?,
+        }),
+    })
+}
+
+fn rearrange(stacks: &mut Stacks, steps: &Steps, crane: &dyn Crane) -> Result<()> {
+    for (from, to, size) in steps {
+        crane.move_crates(stacks, *from, *to, *size)?;
     }
 
+    Ok(())
+}
+
+fn get_tops(stacks: Stacks) -> Result<String> {
     let tops = stacks
         .into_iter()
         .map(|stack| {
@@ -78,10 +266,13 @@ fn convert_input_into_stacks_steps(input: String) -> Result<(Stacks, Steps)> {
         ));
     };
 
-    Ok((
-        convert_input_into_stacks(stacks_part)?,
-        convert_input_into_steps(steps_part)?,
-    ))
+    // Steps start after the drawing and the blank line.
+    let first_line = stacks_part.lines().count() + 2;
+
+    let stacks = convert_input_into_stacks(stacks_part)?;
+    let steps = validate_steps(&stacks, convert_input_into_steps(steps_part)?, first_line)?;
+
+    Ok((stacks, steps))
 }
 
 fn convert_input_into_stacks(input: &str) -> Result<Stacks> {
@@ -95,8 +286,7 @@ fn convert_input_into_stacks(input: &str) -> Result<Stacks> {
     };
     let stack_count = indices
         .split_whitespace()
-        .filter(|token| !token.is_empty())
-        .next_back()
+        .rfind(|token| !token.is_empty())
         .map(str::parse::<u8>)
         .ok_or(anyhow!("Cannot get stack count"))?
         .map_err(|error| anyhow!("Cannot parse stack count: {}", error))?;
@@ -123,6 +313,34 @@ fn convert_input_into_stacks(input: &str) -> Result<Stacks> {
     Ok(stacks)
 }
 
+/// Draws the stacks in the puzzle input format, padding every row to the full width.
+fn render_stacks(stacks: &Stacks) -> String {
+    let height = stacks.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut rows = (0..height)
+        .rev()
+        .map(|level| {
+            stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(byte) => format!("[{}]", *byte as char),
+                    None => String::from("   "),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+    rows.push(
+        (1..=stacks.len())
+            .map(|index| format!(" {index} "))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+
+    rows.join("\n")
+}
+
+/// Parses the steps, keeping the stack numbers as in the input, which counts from 1.
 fn convert_input_into_steps(input: &str) -> Result<Steps> {
     let regex = Regex::new(r"^move (?<size>\d+) from (?<from>\d+) to (?<to>\d+)$")?;
 
@@ -134,14 +352,64 @@ fn convert_input_into_steps(input: &str) -> Result<Steps> {
             };
 
             Ok((
-                captures["from"].parse::<usize>()? - 1,
-                captures["to"].parse::<usize>()? - 1,
+                captures["from"].parse()?,
+                captures["to"].parse()?,
                 captures["size"].parse()?,
             ))
         })
         .collect()
 }
 
+fn describe_heights(heights: &[usize]) -> String {
+    heights
+        .iter()
+        .map(usize::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Checks that every step moves crates between existing stacks and never takes more crates than
+/// a stack holds, by simulating only the stack heights. Returns the steps with the stacks
+/// numbered from 0.
+fn validate_steps(stacks: &Stacks, steps: Steps, first_line: usize) -> Result<Steps> {
+    let mut heights = stacks.iter().map(Vec::len).collect::<Vec<_>>();
+
+    steps
+        .into_iter()
+        .enumerate()
+        .map(|(index, (from, to, size))| {
+            if let Some(stack) = [from, to]
+                .into_iter()
+                .find(|stack| !(1..=heights.len()).contains(stack))
+            {
+                return Err(anyhow!(
+                    "Line {}: no stack {} (heights: {})",
+                    first_line + index,
+                    stack,
+                    describe_heights(&heights)
+                ));
+            }
+
+            let (from, to) = (from - 1, to - 1);
+            if heights[from] < size {
+                return Err(anyhow!(
+                    "Line {}: cannot move {} crates from stack {} holding {} (heights: {})",
+                    first_line + index,
+                    size,
+                    from + 1,
+                    heights[from],
+                    describe_heights(&heights)
+                ));
+            }
+
+            heights[from] -= size;
+            heights[to] += size;
+
+            Ok((from, to, size))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,4 +455,101 @@ move 1 from 1 to 2
 
         Ok(())
     }
+
+    #[test]
+    fn crane_models() -> Result<()> {
+        let input = || {
+            EXAMPLE_INPUT
+                .trim_start_matches("\n")
+                .trim_end()
+                .to_string()
+        };
+
+        assert_eq!(simulate(input(), CraneModel::CrateMover9000, None)?, "CMZ");
+        assert_eq!(simulate(input(), CraneModel::Limited, Some(1))?, "CMZ");
+        assert_eq!(simulate(input(), CraneModel::Limited, Some(3))?, "MCD");
+        assert_eq!(simulate(input(), CraneModel::Limited, Some(2))?, "MCZ");
+        assert_eq!(simulate(input(), CraneModel::Flipping, Some(3))?, "CMZ");
+        assert_eq!(simulate(input(), CraneModel::Flipping, Some(2))?, "CMZ");
+        assert!(simulate(input(), CraneModel::Limited, None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_round_trip() -> Result<()> {
+        let drawing = ["    [D]    ", "[N] [C]    ", "[Z] [M] [P]", " 1   2   3 "].join("\n");
+        let stacks = convert_input_into_stacks(&drawing)?;
+
+        assert_eq!(render_stacks(&stacks), drawing);
+
+        let (mut stacks, steps) = convert_input_into_stacks_steps(
+            EXAMPLE_INPUT
+                .trim_start_matches("\n")
+                .trim_end()
+                .to_string(),
+        )?;
+        for (from, to, size) in steps {
+            CrateMover9000.move_crates(&mut stacks, from, to, size)?;
+
+            assert_eq!(convert_input_into_stacks(&render_stacks(&stacks))?, stacks);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn replay_single_step() -> Result<()> {
+        let input = || {
+            EXAMPLE_INPUT
+                .trim_start_matches("\n")
+                .trim_end()
+                .to_string()
+        };
+
+        assert_eq!(
+            replay(input(), CraneModel::CrateMover9000, None, Some(2))?,
+            [
+                "After step 2: move 3 from 1 to 3",
+                "        [Z]",
+                "        [N]",
+                "    [C] [D]",
+                "    [M] [P]",
+                " 1   2   3 ",
+                "",
+                "",
+            ]
+            .join("\n")
+        );
+        assert!(replay(input(), CraneModel::CrateMover9000, None, Some(5)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_steps() {
+        let drawing = EXAMPLE_INPUT
+            .trim_start_matches("\n")
+            .split_once("\n\n")
+            .unwrap()
+            .0;
+        let error = |steps: &str| {
+            part_2(format!("{drawing}\n\n{steps}"))
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("move 1 from 2 to 1\nmove 4 from 1 to 3"),
+            "Line 7: cannot move 4 crates from stack 1 holding 3 (heights: 3 2 1)"
+        );
+        assert_eq!(
+            error("move 1 from 4 to 1"),
+            "Line 6: no stack 4 (heights: 2 3 1)"
+        );
+        assert_eq!(
+            error("move 1 from 2 to 1\nmove 1 from 1 to 0"),
+            "Line 7: no stack 0 (heights: 3 2 1)"
+        );
+    }
 }
@@ -1,70 +1,340 @@
-use std::hash::Hash;
+use std::{
+    cmp::Reverse,
+    fmt::Write,
+    io::{self, BufReader, Read},
+    iter,
+};
 
 use anyhow::{Result, anyhow};
-use itertools::Itertools;
-
-use cli::{Part, get_part};
+use clap::Subcommand;
+
+use cli::{CountingAllocator, Part, Task, get_task_reader, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Finds the first marker of any number of distinct characters.
+    Marker {
+        #[arg(short, long)]
+        window: usize,
+    },
+    /// Splits the datastream into packets and messages and prints their payloads.
+    Decode,
+}
 
 fn main() {
-    match get_part("inputs/day-6.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task_reader::<Mode>("inputs/day-6.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(Mode::Marker { window }, input)) => {
+            println!("{:?}", find_marker(input, window))
+        }
+        Ok(Task::Mode(Mode::Decode, input)) => match decode(input) {
+            Ok(report) => print!("{report}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
-fn part_1(input: String) -> Result<usize> {
-    let mut marker = None;
+fn part_1(input: impl Read) -> Result<usize> {
+    find_marker(input, 4)
+}
+
+fn part_2(input: impl Read) -> Result<usize> {
+    find_marker(input, 14)
+}
 
-    let datastream = input.bytes().collect::<Vec<_>>();
-    for (col, _) in datastream.iter().enumerate() {
-        if col < 3 {
-            continue;
+/// Finds windows of distinct lowercase letters in a datastream, one byte at a time.
+///
+/// Instead of keeping the window, it remembers where each letter was last seen. The current run
+/// of distinct letters starts right after the latest repeat, so it is a marker as soon as it is
+/// long enough.
+struct MarkerDetector {
+    window: usize,
+    /// Number of bytes pushed so far.
+    position: usize,
+    /// Position where the current run of distinct letters starts.
+    start: usize,
+    /// Position right after the last occurrence of each letter, or 0 if it was never seen.
+    last_seen: [usize; 26],
+}
+
+impl MarkerDetector {
+    fn new(window: usize) -> Result<Self> {
+        if window == 0 || window > 26 {
+            return Err(anyhow!(
+                "Invalid window size: {} (must be between 1 and 26)",
+                window
+            ));
+        }
+
+        Ok(MarkerDetector {
+            window,
+            position: 0,
+            start: 0,
+            last_seen: [0; 26],
+        })
+    }
+
+    /// Feeds the next byte, returning whether it completes a marker.
+    fn push(&mut self, byte: u8) -> Result<bool> {
+        if !byte.is_ascii_lowercase() {
+            return Err(anyhow!(
+                "Cannot use byte {:?} at offset {} in datastream",
+                byte as char,
+                self.position
+            ));
+        }
+
+        let slot = &mut self.last_seen[(byte - b'a') as usize];
+        self.start = self.start.max(*slot);
+        self.position += 1;
+        *slot = self.position;
+
+        Ok(self.position - self.start >= self.window)
+    }
+
+    /// Forgets the bytes pushed so far, so the next marker cannot overlap earlier ones.
+    fn reset(&mut self) {
+        self.start = self.position;
+    }
+}
+
+/// Reads a datastream until the first marker of window distinct characters, returning the number
+/// of characters up to and including it.
+///
+/// Only a small read buffer is kept, never the stream itself.
+fn find_marker(input: impl Read, window: usize) -> Result<usize> {
+    let mut detector = MarkerDetector::new(window)?;
+
+    for byte in datastream_bytes(input) {
+        if detector.push(byte?)? {
+            return Ok(detector.position);
         }
+    }
+
+    Err(anyhow!(
+        "Cannot find marker of {} distinct characters",
+        window
+    ))
+}
+
+/// Reads the bytes of the datastream, a single line that may come after blank lines.
+fn datastream_bytes(input: impl Read) -> impl Iterator<Item = io::Result<u8>> {
+    BufReader::new(input)
+        .bytes()
+        .skip_while(|byte| matches!(byte, Ok(b'\n')))
+        .take_while(|byte| !matches!(byte, Ok(b'\n')))
+}
+
+const PACKET_WINDOW: usize = 4;
+const MESSAGE_WINDOW: usize = 14;
 
-        if all_unique(&datastream[(col - 3)..=col]) {
-            marker = Some(col + 1);
-            break;
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MarkerKind {
+    Packet,
+    Message,
+}
+
+impl MarkerKind {
+    fn window(self) -> usize {
+        match self {
+            MarkerKind::Packet => PACKET_WINDOW,
+            MarkerKind::Message => MESSAGE_WINDOW,
         }
     }
+}
+
+/// A marker found in the datastream, ending right before offset end.
+#[derive(Debug, Eq, PartialEq)]
+struct Marker {
+    kind: MarkerKind,
+    end: usize,
+}
+
+impl Marker {
+    fn start(&self) -> usize {
+        self.end - self.kind.window()
+    }
+}
 
-    marker.ok_or(anyhow!("Cannot find marker in input: {}", input))
+/// The payload of a message, running from its message marker to the next packet marker.
+#[derive(Debug, Eq, PartialEq)]
+struct Message {
+    offset: usize,
+    payload: String,
 }
 
-fn part_2(input: String) -> Result<usize> {
-    let mut marker = None;
+struct Datastream {
+    markers: Vec<Marker>,
+    messages: Vec<Message>,
+    length: usize,
+}
+
+/// Splits a datastream into packets and messages.
+///
+/// Each packet starts with a start-of-packet marker and is followed by a start-of-message marker,
+/// after which the message payload runs until the next start-of-packet marker or the end of the
+/// stream. Markers never overlap, so each detector starts afresh after the previous marker.
+fn split_datastream(input: impl Read) -> Result<Datastream> {
+    let mut packets = MarkerDetector::new(PACKET_WINDOW)?;
+    let mut messages = MarkerDetector::new(MESSAGE_WINDOW)?;
+    let mut datastream = Datastream {
+        markers: Vec::new(),
+        messages: Vec::new(),
+        length: 0,
+    };
+    let mut expected = MarkerKind::Packet;
+    let mut payload = None;
+
+    for byte in datastream_bytes(input) {
+        let byte = byte?;
+        let packet_found = packets.push(byte)?;
+        let message_found = messages.push(byte)?;
+        let position = packets.position;
+
+        if let Some(Message { payload, .. }) = &mut payload {
+            payload.push(byte as char);
+        }
 
-    let datastream = input.bytes().collect::<Vec<_>>();
-    for (col, _) in datastream.iter().enumerate() {
-        if col < 13 {
+        let found = match expected {
+            MarkerKind::Packet => packet_found,
+            MarkerKind::Message => message_found,
+        };
+        if !found {
             continue;
         }
 
-        if all_unique(&datastream[(col - 13)..=col]) {
-            marker = Some(col + 1);
-            break;
+        datastream.markers.push(Marker {
+            kind: expected,
+            end: position,
+        });
+        match expected {
+            MarkerKind::Packet => {
+                // The new packet marker ends the previous message.
+                if let Some(mut message) = payload.take() {
+                    message
+                        .payload
+                        .truncate(position - PACKET_WINDOW - message.offset);
+                    datastream.messages.push(message);
+                }
+
+                messages.reset();
+                expected = MarkerKind::Message;
+            }
+            MarkerKind::Message => {
+                payload = Some(Message {
+                    offset: position,
+                    payload: String::new(),
+                });
+
+                packets.reset();
+                expected = MarkerKind::Packet;
+            }
         }
     }
+    datastream.length = packets.position;
+    datastream.messages.extend(payload);
+
+    Ok(datastream)
+}
+
+/// Minimum, mean and maximum distance between consecutive markers of one kind.
+fn marker_spacing(markers: &[Marker], kind: MarkerKind) -> Option<(usize, f64, usize)> {
+    let spacings = markers
+        .iter()
+        .filter(|marker| marker.kind == kind)
+        .map(|marker| marker.end)
+        .collect::<Vec<_>>()
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .collect::<Vec<_>>();
+
+    Some((
+        *spacings.iter().min()?,
+        spacings.iter().sum::<usize>() as f64 / spacings.len() as f64,
+        *spacings.iter().max()?,
+    ))
+}
 
-    marker.ok_or(anyhow!("Cannot find marker in input: {}", input))
+/// Finds the longest stretch of the datastream not covered by any marker, as (offset, length).
+fn longest_run_without_marker(datastream: &Datastream) -> (usize, usize) {
+    let starts = datastream.markers.iter().map(Marker::start);
+    let ends = datastream.markers.iter().map(|marker| marker.end);
+
+    // Runs lie between the end of one marker and the start of the next.
+    iter::once(0)
+        .chain(ends)
+        .zip(starts.chain(iter::once(datastream.length)))
+        .map(|(end, start)| (end, start - end))
+        .max_by_key(|(offset, length)| (*length, Reverse(*offset)))
+        .unwrap_or((0, 0))
 }
 
-/// Checks that the entire slice is composed of unique elements.
-fn all_unique<T>(slice: &[T]) -> bool
-where
-    T: Eq + Hash,
-{
-    slice.iter().unique().count() == slice.len()
+fn decode(input: impl Read) -> Result<String> {
+    let datastream = split_datastream(input)?;
+    let mut report = String::new();
+
+    for message in &datastream.messages {
+        writeln!(
+            report,
+            "Message at {} ({} bytes): {}",
+            message.offset,
+            message.payload.len(),
+            message.payload
+        )?;
+    }
+
+    let count = |kind| {
+        datastream
+            .markers
+            .iter()
+            .filter(|marker| marker.kind == kind)
+            .count()
+    };
+    writeln!(
+        report,
+        "Packets: {}, messages: {}, bytes: {}",
+        count(MarkerKind::Packet),
+        count(MarkerKind::Message),
+        datastream.length
+    )?;
+
+    for (name, kind) in [
+        ("Packet", MarkerKind::Packet),
+        ("Message", MarkerKind::Message),
+    ] {
+        match marker_spacing(&datastream.markers, kind) {
+            Some((min, mean, max)) => writeln!(
+                report,
+                "{name} marker spacing: min {min}, mean {mean:.2}, max {max}"
+            )?,
+            None => writeln!(report, "{name} marker spacing: -")?,
+        }
+    }
+
+    let (offset, length) = longest_run_without_marker(&datastream);
+    writeln!(
+        report,
+        "Longest run without a marker: {length} bytes at offset {offset}"
+    )?;
+
+    Ok(report)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE_INPUT: &str = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+
     #[test]
     fn example_1a() -> Result<()> {
-        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
-        assert_eq!(part_1(String::from(input))?, 7);
+        let input = EXAMPLE_INPUT;
+        assert_eq!(part_1(input.as_bytes())?, 7);
 
         Ok(())
     }
@@ -72,7 +342,7 @@ mod tests {
     #[test]
     fn example_1b() -> Result<()> {
         let input = "bvwbjplbgvbhsrlpgdmjqwftvncz";
-        assert_eq!(part_1(String::from(input))?, 5);
+        assert_eq!(part_1(input.as_bytes())?, 5);
 
         Ok(())
     }
@@ -80,7 +350,7 @@ mod tests {
     #[test]
     fn example_1c() -> Result<()> {
         let input = "nppdvjthqldpwncqszvftbrmjlhg";
-        assert_eq!(part_1(String::from(input))?, 6);
+        assert_eq!(part_1(input.as_bytes())?, 6);
 
         Ok(())
     }
@@ -88,7 +358,7 @@ mod tests {
     #[test]
     fn example_1d() -> Result<()> {
         let input = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
-        assert_eq!(part_1(String::from(input))?, 10);
+        assert_eq!(part_1(input.as_bytes())?, 10);
 
         Ok(())
     }
@@ -96,15 +366,15 @@ mod tests {
     #[test]
     fn example_1e() -> Result<()> {
         let input = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw";
-        assert_eq!(part_1(String::from(input))?, 11);
+        assert_eq!(part_1(input.as_bytes())?, 11);
 
         Ok(())
     }
 
     #[test]
     fn example_2a() -> Result<()> {
-        let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
-        assert_eq!(part_2(String::from(input))?, 19);
+        let input = EXAMPLE_INPUT;
+        assert_eq!(part_2(input.as_bytes())?, 19);
 
         Ok(())
     }
@@ -112,7 +382,7 @@ mod tests {
     #[test]
     fn example_2b() -> Result<()> {
         let input = "bvwbjplbgvbhsrlpgdmjqwftvncz";
-        assert_eq!(part_2(String::from(input))?, 23);
+        assert_eq!(part_2(input.as_bytes())?, 23);
 
         Ok(())
     }
@@ -120,7 +390,7 @@ mod tests {
     #[test]
     fn example_2c() -> Result<()> {
         let input = "nppdvjthqldpwncqszvftbrmjlhg";
-        assert_eq!(part_2(String::from(input))?, 23);
+        assert_eq!(part_2(input.as_bytes())?, 23);
 
         Ok(())
     }
@@ -128,7 +398,7 @@ mod tests {
     #[test]
     fn example_2d() -> Result<()> {
         let input = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
-        assert_eq!(part_2(String::from(input))?, 29);
+        assert_eq!(part_2(input.as_bytes())?, 29);
 
         Ok(())
     }
@@ -136,7 +406,71 @@ mod tests {
     #[test]
     fn example_2e() -> Result<()> {
         let input = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw";
-        assert_eq!(part_2(String::from(input))?, 26);
+        assert_eq!(part_2(input.as_bytes())?, 26);
+
+        Ok(())
+    }
+
+    #[test]
+    fn any_window() -> Result<()> {
+        let input = EXAMPLE_INPUT;
+
+        assert_eq!(find_marker(input.as_bytes(), 1)?, 1);
+        assert_eq!(find_marker(input.as_bytes(), 3)?, 3);
+        assert!(find_marker(input.as_bytes(), 20).is_err());
+        assert!(find_marker(input.as_bytes(), 27).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_byte() {
+        assert!(part_1("abA".as_bytes()).is_err());
+        assert_eq!(part_1("abcd\nA".as_bytes()).ok(), Some(4));
+        assert_eq!(
+            part_1("\n\nmjqjpqmgbljsphdztnvjfqwrcgsmlb".as_bytes()).ok(),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn split_packets() -> Result<()> {
+        // Packet marker abcd, message marker efghijklmnopqr, payload sss, then the next packet.
+        let input = "aabcdefghijklmnopqrsssstuvbcdefghijklmnoxxx";
+
+        let datastream = split_datastream(input.as_bytes())?;
+
+        assert_eq!(
+            datastream
+                .markers
+                .iter()
+                .map(|marker| (marker.kind, marker.end))
+                .collect::<Vec<_>>(),
+            [
+                (MarkerKind::Packet, 5),
+                (MarkerKind::Message, 19),
+                (MarkerKind::Packet, 26),
+                (MarkerKind::Message, 40),
+            ]
+        );
+        assert_eq!(
+            datastream.messages,
+            [
+                Message {
+                    offset: 19,
+                    payload: String::from("sss"),
+                },
+                Message {
+                    offset: 40,
+                    payload: String::from("xxx"),
+                },
+            ]
+        );
+        assert_eq!(longest_run_without_marker(&datastream), (19, 3));
+        assert_eq!(
+            marker_spacing(&datastream.markers, MarkerKind::Packet),
+            Some((21, 21.0, 21))
+        );
 
         Ok(())
     }
@@ -1,21 +1,93 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Write},
+    fs::{self, File},
+    iter,
+    ops::Range,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Result, anyhow};
+use clap::{Subcommand, ValueEnum};
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, Task, get_task, profile};
+
+const DISK_SPACE: u32 = 70000000;
+const SPACE_NEEDED: u32 = 30000000;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Prints the filesystem recorded by the transcript.
+    Show {
+        #[arg(short, long, default_value = "tree")]
+        format: View,
+    },
+    /// Prints the size of a file, or the total size of a directory.
+    Size { path: String },
+    /// Lists the problems found in the transcript.
+    Check,
+    /// Plans which directories to delete to make room for the update.
+    Cleanup {
+        /// Deletes as few directories as possible, rather than as little data as possible.
+        #[arg(long)]
+        fewest: bool,
+
+        #[arg(long, default_value_t = DISK_SPACE)]
+        disk_space: u32,
+
+        #[arg(long, default_value_t = SPACE_NEEDED)]
+        space_needed: u32,
+    },
+    /// Creates the filesystem under an empty directory, with sparse files, and checks the
+    /// directory sizes against it.
+    Materialise { target: PathBuf },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum View {
+    /// Like `tree`, with file sizes.
+    Tree,
+    /// Like `du -h`, one line per directory.
+    Du,
+    Json,
+}
 
 fn main() {
-    match get_part("inputs/day-7.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task::<Mode>("inputs/day-7.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(Mode::Show { format }, input)) => match show(input, format) {
+            Ok(view) => print!("{view}"),
+            Err(error) => println!("{error:?}"),
+        },
+        Ok(Task::Mode(Mode::Size { path }, input)) => println!("{:?}", path_size(input, &path)),
+        Ok(Task::Mode(Mode::Check, input)) => print!("{}", check(input)),
+        Ok(Task::Mode(
+            Mode::Cleanup {
+                fewest,
+                disk_space,
+                space_needed,
+            },
+            input,
+        )) => match cleanup(input, fewest, disk_space, space_needed) {
+            Ok(report) => print!("{report}"),
+            Err(error) => println!("{error:?}"),
+        },
+        Ok(Task::Mode(Mode::Materialise { target }, input)) => match materialise(input, &target) {
+            Ok(report) => print!("{report}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
 fn part_1(input: String) -> Result<u32> {
-    let dirs = parse_input_into_dirs(input)?;
+    let filesystem = parse_transcript(input)?;
 
-    let dir_sizes = dir_sizes(&dirs)?;
+    let dir_sizes = dir_sizes(&filesystem)?;
 
     Ok(dir_sizes
         .into_values()
@@ -24,11 +96,11 @@ fn part_1(input: String) -> Result<u32> {
 }
 
 fn part_2(input: String) -> Result<u32> {
-    let dirs = parse_input_into_dirs(input)?;
+    let filesystem = parse_transcript(input)?;
 
-    let dir_sizes = dir_sizes(&dirs)?;
+    let dir_sizes = dir_sizes(&filesystem)?;
 
-    let space_required = 30000000 - (70000000 - dir_sizes["/"]);
+    let space_required = SPACE_NEEDED - (DISK_SPACE - dir_sizes["/"]);
 
     Ok(dir_sizes.into_values().fold(u32::MAX, |acc, dir_size| {
         if dir_size > space_required && dir_size < acc {
@@ -39,92 +111,889 @@ fn part_2(input: String) -> Result<u32> {
     }))
 }
 
-/// Represents a directory.
-#[derive(Default)]
-struct Dir {
-    /// The total size of files directly inside this directory.
-    size: u32,
+/// Represents a directory or a file.
+struct Node {
+    name: String,
+    parent: Option<usize>,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    /// Indices of the children by name.
+    Dir(BTreeMap<String, usize>),
+    File(u32),
+}
+
+/// The filesystem recorded by a transcript, with the root directory at index 0.
+///
+/// Nodes are only ever added below existing directories, so every node comes after its parent.
+struct Filesystem {
+    nodes: Vec<Node>,
+}
+
+impl Filesystem {
+    const ROOT: usize = 0;
+
+    fn new() -> Self {
+        Filesystem {
+            nodes: vec![Node {
+                name: String::from("/"),
+                parent: None,
+                kind: NodeKind::Dir(BTreeMap::new()),
+            }],
+        }
+    }
+
+    fn children(&self, dir: usize) -> impl Iterator<Item = usize> + '_ {
+        let children = match &self.nodes[dir].kind {
+            NodeKind::Dir(children) => Some(children.values().copied()),
+            NodeKind::File(_) => None,
+        };
+
+        children.into_iter().flatten()
+    }
+
+    fn child(&self, dir: usize, name: &str) -> Option<usize> {
+        match &self.nodes[dir].kind {
+            NodeKind::Dir(children) => children.get(name).copied(),
+            NodeKind::File(_) => None,
+        }
+    }
+
+    /// Adds a node to a directory, returning the existing one if the name is already taken.
+    fn add(&mut self, dir: usize, name: &str, kind: NodeKind) -> Result<usize> {
+        if let Some(existing) = self.child(dir, name) {
+            return Ok(existing);
+        }
+
+        let index = self.nodes.len();
+        let NodeKind::Dir(children) = &mut self.nodes[dir].kind else {
+            return Err(anyhow!("Cannot add {} to file: {}", name, self.path(dir)));
+        };
+        children.insert(String::from(name), index);
+
+        self.nodes.push(Node {
+            name: String::from(name),
+            parent: Some(dir),
+            kind,
+        });
+
+        Ok(index)
+    }
+
+    /// Gets the path of a node. Directory paths end with a slash, e.g. "/a/e/".
+    fn path(&self, node: usize) -> String {
+        let mut names = Vec::new();
+        let mut current = Some(node);
+        while let Some(index) = current {
+            if index != Self::ROOT {
+                names.push(self.nodes[index].name.as_str());
+            }
+            current = self.nodes[index].parent;
+        }
+        names.reverse();
+
+        let mut path = String::from("/") + &names.join("/");
+        if matches!(self.nodes[node].kind, NodeKind::Dir(_)) && node != Self::ROOT {
+            path.push('/');
+        }
+
+        path
+    }
+
+    /// Finds a node by path, with or without the trailing slash of a directory.
+    fn find(&self, path: &str) -> Option<usize> {
+        path.split("/")
+            .filter(|name| !name.is_empty())
+            .try_fold(Self::ROOT, |dir, name| self.child(dir, name))
+    }
+
+    /// Calculates the size of every node, adding each one to its parent from the last node up.
+    fn sizes(&self) -> Result<Vec<u32>> {
+        let mut sizes = self
+            .nodes
+            .iter()
+            .map(|node| match node.kind {
+                NodeKind::Dir(_) => 0,
+                NodeKind::File(size) => size,
+            })
+            .collect::<Vec<_>>();
+
+        for index in (0..self.nodes.len()).rev() {
+            let Some(parent) = self.nodes[index].parent else {
+                continue;
+            };
+
+            let Some(size) = sizes[parent].checked_add(sizes[index]) else {
+                return Err(anyhow!("Size of {} overflows u32", self.path(parent)));
+            };
+            sizes[parent] = size;
+        }
+
+        Ok(sizes)
+    }
+}
+
+/// A problem found in a transcript.
+struct Diagnostic {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+/// An entry in the output of `ls`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Entry {
+    Dir,
+    File(u32),
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Dir => write!(f, "a directory"),
+            Entry::File(size) => write!(f, "a file of {size}"),
+        }
+    }
+}
+
+/// The output of one `ls`, starting on line.
+struct Listing {
+    line: usize,
+    entries: BTreeMap<String, Entry>,
+}
 
-    /// Subfolders (path strings) of this directory.
-    child_dirs: Vec<String>,
+/// Replays a transcript into a [Filesystem], collecting diagnostics instead of stopping at the
+/// first problem.
+///
+/// Only the first listing of a directory adds to the filesystem. Later ones must match it.
+/// Directories entered with `cd` before being listed are added right away, and must show up in
+/// the listing when it comes.
+struct TranscriptParser {
+    filesystem: Filesystem,
+    diagnostics: Vec<Diagnostic>,
+    cwd: usize,
+    /// The listing being read, of the cwd.
+    listing: Option<Listing>,
+    /// The first listing of each directory.
+    listings: HashMap<usize, Listing>,
+    /// The line on which each directory first came up.
+    mentioned: HashMap<usize, usize>,
 }
 
-fn parse_input_into_dirs(input: String) -> Result<HashMap<String, Dir>> {
-    // The stack of directories in the cwd.
-    let mut cwd: Vec<String> = vec![String::from("/")];
+impl TranscriptParser {
+    fn new() -> Self {
+        TranscriptParser {
+            filesystem: Filesystem::new(),
+            diagnostics: Vec::new(),
+            cwd: Filesystem::ROOT,
+            listing: None,
+            listings: HashMap::new(),
+            mentioned: HashMap::from([(Filesystem::ROOT, 1)]),
+        }
+    }
+
+    fn report(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic { line, message });
+    }
 
-    let mut dirs: HashMap<String, Dir> = HashMap::new();
-    dirs.entry(cwd.join("")).or_default();
+    fn parse_line(&mut self, number: usize, line: &str) {
+        if line.starts_with("$ ") {
+            self.finish_listing();
+        }
 
-    for line in input.lines() {
         match line {
-            "$ cd /" => cwd = vec![String::from("/")],
-            "$ cd .." => {
-                cwd.pop();
+            "$ cd /" => self.cwd = Filesystem::ROOT,
+            "$ cd .." => match self.filesystem.nodes[self.cwd].parent {
+                Some(parent) => self.cwd = parent,
+                None => self.report(number, String::from("cannot cd above the root")),
+            },
+            "$ ls" => {
+                self.listing = Some(Listing {
+                    line: number,
+                    entries: BTreeMap::new(),
+                })
             }
-            cd if cd.starts_with("$ cd ") => {
-                let Some((_, dirname)) = cd.split_once(" cd ") else {
-                    return Err(anyhow!("Cannot split cd: {}", cd));
-                };
+            cd if cd.starts_with("$ cd ") => self.change_dir(number, &cd[5..]),
+            output if !output.starts_with("$ ") => self.parse_output(number, output),
+            _ => self.report(number, format!("cannot parse command: {line}")),
+        }
+    }
+
+    fn change_dir(&mut self, number: usize, dirname: &str) {
+        let path = self.filesystem.path(self.cwd);
+
+        match self.filesystem.child(self.cwd, dirname) {
+            Some(child) if matches!(self.filesystem.nodes[child].kind, NodeKind::Dir(_)) => {
+                self.cwd = child;
+            }
+            Some(_) => self.report(number, format!("cannot cd into file {path}{dirname}")),
+            None => {
+                if let Some(listing) = self.listings.get(&self.cwd) {
+                    let message = format!(
+                        "cd into {dirname}, which the listing of {path} on line {} does not include",
+                        listing.line
+                    );
+                    self.report(number, message);
+                }
+
+                match self.add(self.cwd, dirname, Entry::Dir, number) {
+                    Ok(child) => self.cwd = child,
+                    Err(error) => self.report(number, error.to_string()),
+                }
+            }
+        }
+    }
+
+    fn parse_output(&mut self, number: usize, output: &str) {
+        let entry = match output.split_once(" ") {
+            Some(("dir", dirname)) => Some((dirname, Entry::Dir)),
+            Some((filesize, filename)) => filesize
+                .parse()
+                .ok()
+                .map(|filesize| (filename, Entry::File(filesize))),
+            None => None,
+        };
+        let Some((name, entry)) = entry else {
+            return self.report(number, format!("cannot parse output: {output}"));
+        };
+
+        let Some(listing) = &mut self.listing else {
+            return self.report(number, format!("output outside of a listing: {output}"));
+        };
+        if listing.entries.insert(String::from(name), entry).is_some() {
+            self.report(number, format!("{name} is listed twice"));
+        }
+    }
+
+    /// Adds the listing being read to the filesystem, or checks it against the first listing.
+    fn finish_listing(&mut self) {
+        let Some(listing) = self.listing.take() else {
+            return;
+        };
+        let path = self.filesystem.path(self.cwd);
 
-                cwd.push(String::from(dirname) + "/");
-                dirs.entry(cwd.join("")).or_default();
+        if let Some(first) = self.listings.get(&self.cwd) {
+            if first.entries != listing.entries {
+                let message = format!(
+                    "listing of {path} contradicts the listing on line {}: {}",
+                    first.line,
+                    describe_differences(&first.entries, &listing.entries)
+                );
+                self.report(listing.line, message);
             }
-            dir if dir.starts_with("dir ") => {
-                let Some((_, dirname)) = dir.split_once(" ") else {
-                    return Err(anyhow!("Cannot split dir: {}", dir));
-                };
 
-                let mut child_dir = cwd.clone();
-                child_dir.push(String::from(dirname) + "/");
+            return;
+        }
+
+        let entered = self
+            .filesystem
+            .children(self.cwd)
+            .map(|child| self.filesystem.nodes[child].name.clone())
+            .filter(|name| !listing.entries.contains_key(name))
+            .collect::<Vec<_>>();
+        for name in entered {
+            let message = format!("listing of {path} does not include {name}, entered before");
+            self.report(listing.line, message);
+        }
 
-                dirs.entry(cwd.join(""))
-                    .and_modify(|dir| dir.child_dirs.push(child_dir.join("")));
+        for (name, entry) in &listing.entries {
+            match self.filesystem.child(self.cwd, name) {
+                Some(child) if matches!(self.filesystem.nodes[child].kind, NodeKind::Dir(_)) => {
+                    if *entry != Entry::Dir {
+                        let message =
+                            format!("listing of {path} shows {name}, entered before, as {entry}");
+                        self.report(listing.line, message);
+                    }
+                }
+                _ => {
+                    if let Err(error) = self.add(self.cwd, name, *entry, listing.line) {
+                        self.report(listing.line, error.to_string());
+                    }
+                }
             }
-            file if file.as_bytes()[0].is_ascii_digit() => {
-                let Some((filesize, _)) = file.split_once(" ") else {
-                    return Err(anyhow!("Cannot split file: {}", file));
-                };
+        }
+
+        self.listings.insert(self.cwd, listing);
+    }
+
+    fn add(&mut self, dir: usize, name: &str, entry: Entry, line: usize) -> Result<usize> {
+        let kind = match entry {
+            Entry::Dir => NodeKind::Dir(BTreeMap::new()),
+            Entry::File(size) => NodeKind::File(size),
+        };
+
+        let index = self.filesystem.add(dir, name, kind)?;
+        if entry == Entry::Dir {
+            self.mentioned.entry(index).or_insert(line);
+        }
+
+        Ok(index)
+    }
+
+    fn finish(mut self) -> (Filesystem, Vec<Diagnostic>) {
+        self.finish_listing();
+
+        let mut unlisted = self
+            .mentioned
+            .iter()
+            .filter(|(dir, _)| !self.listings.contains_key(dir))
+            .map(|(dir, line)| (*line, self.filesystem.path(*dir)))
+            .collect::<Vec<_>>();
+        unlisted.sort();
+        for (line, path) in unlisted {
+            self.report(
+                line,
+                format!("{path} is never listed, so its size is unknown"),
+            );
+        }
+
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+
+        (self.filesystem, self.diagnostics)
+    }
+}
 
-                let filesize = filesize.parse::<u32>()?;
+fn describe_differences(
+    first: &BTreeMap<String, Entry>,
+    second: &BTreeMap<String, Entry>,
+) -> String {
+    let names = first.keys().chain(second.keys()).collect::<BTreeSet<_>>();
 
-                dirs.entry(cwd.join(""))
-                    .and_modify(|dir| dir.size += filesize);
+    names
+        .into_iter()
+        .filter_map(|name| match (first.get(name), second.get(name)) {
+            (Some(before), Some(after)) if before != after => {
+                Some(format!("{name} was {before}, now {after}"))
             }
-            "$ ls" => (),
-            _ => unreachable!(),
+            (Some(_), None) => Some(format!("{name} is missing")),
+            (None, Some(after)) => Some(format!("{name} is new, {after}")),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn read_transcript(input: &str) -> (Filesystem, Vec<Diagnostic>) {
+    let mut parser = TranscriptParser::new();
+
+    for (index, line) in input.lines().enumerate() {
+        parser.parse_line(index + 1, line);
+    }
+
+    parser.finish()
+}
+
+/// Parses a transcript, failing if it has any problem that could make the sizes wrong.
+fn parse_transcript(input: String) -> Result<Filesystem> {
+    let (filesystem, diagnostics) = read_transcript(&input);
+
+    if !diagnostics.is_empty() {
+        return Err(anyhow!(
+            "Cannot trust transcript:\n{}",
+            diagnostics
+                .iter()
+                .map(Diagnostic::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+
+    Ok(filesystem)
+}
+
+fn check(input: String) -> String {
+    let (_, diagnostics) = read_transcript(&input);
+
+    if diagnostics.is_empty() {
+        return String::from("No problems found\n");
+    }
+
+    diagnostics
+        .iter()
+        .map(|diagnostic| format!("{diagnostic}\n"))
+        .collect()
+}
+
+/// Calculates the size of each directory, by path.
+fn dir_sizes(filesystem: &Filesystem) -> Result<HashMap<String, u32>> {
+    let sizes = filesystem.sizes()?;
+
+    Ok(filesystem
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| matches!(node.kind, NodeKind::Dir(_)))
+        .map(|(index, _)| (filesystem.path(index), sizes[index]))
+        .collect())
+}
+
+fn path_size(input: String, path: &str) -> Result<u32> {
+    let filesystem = parse_transcript(input)?;
+
+    let Some(node) = filesystem.find(path) else {
+        return Err(anyhow!("Cannot find path: {}", path));
+    };
+
+    Ok(filesystem.sizes()?[node])
+}
+
+fn show(input: String, format: View) -> Result<String> {
+    let filesystem = parse_transcript(input)?;
+    let sizes = filesystem.sizes()?;
+
+    let mut view = String::new();
+    match format {
+        View::Tree => {
+            writeln!(view, "/")?;
+            format_tree(&filesystem, Filesystem::ROOT, "", &mut view)?;
         }
+        View::Du => format_du(&filesystem, &sizes, Filesystem::ROOT, &mut view)?,
+        View::Json => writeln!(
+            view,
+            "{}",
+            format_json(&filesystem, &sizes, Filesystem::ROOT)
+        )?,
     }
 
-    Ok(dirs)
+    Ok(view)
 }
 
-/// Calculates the size of each directory in dirs.
-fn dir_sizes(dirs: &HashMap<String, Dir>) -> Result<HashMap<String, u32>> {
-    let mut cache = HashMap::new();
+/// Draws the children of a directory with box-drawing lines, prefixing each line with the
+/// branches of its ancestors.
+fn format_tree(filesystem: &Filesystem, dir: usize, prefix: &str, view: &mut String) -> Result<()> {
+    let children = filesystem.children(dir).collect::<Vec<_>>();
 
-    dirs.keys().for_each(|path| {
-        dir_size(path, dirs, &mut cache);
-    });
+    for (position, child) in children.iter().enumerate() {
+        let is_last = position + 1 == children.len();
+        let node = &filesystem.nodes[*child];
+
+        let branch = if is_last { "└── " } else { "├── " };
+        match node.kind {
+            NodeKind::Dir(_) => writeln!(view, "{prefix}{branch}{}", node.name)?,
+            NodeKind::File(size) => writeln!(view, "{prefix}{branch}{} ({size})", node.name)?,
+        }
 
-    Ok(cache)
+        let prefix = prefix.to_string() + if is_last { "    " } else { "│   " };
+        format_tree(filesystem, *child, &prefix, view)?;
+    }
+
+    Ok(())
 }
 
-/// Calculates the size of a directory and its children.
-fn dir_size(path: &String, dirs: &HashMap<String, Dir>, cache: &mut HashMap<String, u32>) -> u32 {
-    if cache.contains_key(path) {
-        return cache[path];
+/// Lists directories after their subdirectories, as `du` does.
+fn format_du(filesystem: &Filesystem, sizes: &[u32], dir: usize, view: &mut String) -> Result<()> {
+    for child in filesystem.children(dir) {
+        if matches!(filesystem.nodes[child].kind, NodeKind::Dir(_)) {
+            format_du(filesystem, sizes, child, view)?;
+        }
     }
 
-    let dir = &dirs[path];
-    let dir_size = dir.size
-        + dir
-            .child_dirs
-            .iter()
-            .map(|child_dir| dir_size(child_dir, dirs, cache))
-            .sum::<u32>();
+    let path = filesystem.path(dir);
+    let path = if dir == Filesystem::ROOT {
+        path.as_str()
+    } else {
+        path.trim_end_matches("/")
+    };
+    writeln!(view, "{}\t{path}", format_human(sizes[dir]))?;
+
+    Ok(())
+}
+
+/// Formats a size in bytes with a binary suffix, e.g. "1.5K" or "23M".
+fn format_human(size: u32) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+
+    if size < 1024 {
+        return size.to_string();
+    }
 
-    *cache.entry(path.clone()).or_insert(dir_size)
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if value < 10.0 {
+        format!("{value:.1}{}", UNITS[unit])
+    } else {
+        format!("{value:.0}{}", UNITS[unit])
+    }
+}
+
+fn format_json(filesystem: &Filesystem, sizes: &[u32], node: usize) -> String {
+    let name = filesystem.nodes[node]
+        .name
+        .replace("\\", "\\\\")
+        .replace("\"", "\\\"");
+
+    match filesystem.nodes[node].kind {
+        NodeKind::Dir(_) => format!(
+            "{{\"name\":\"{name}\",\"type\":\"dir\",\"size\":{},\"children\":[{}]}}",
+            sizes[node],
+            filesystem
+                .children(node)
+                .map(|child| format_json(filesystem, sizes, child))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        NodeKind::File(size) => {
+            format!("{{\"name\":\"{name}\",\"type\":\"file\",\"size\":{size}}}")
+        }
+    }
+}
+
+/// A set of sums from 0 up to a bound, as a bitset.
+#[derive(Clone)]
+struct Sums {
+    words: Vec<u64>,
+    bound: usize,
+}
+
+impl Sums {
+    fn zero(bound: usize) -> Self {
+        let mut words = vec![0; bound / 64 + 1];
+        words[0] = 1;
+
+        Sums { words, bound }
+    }
+
+    fn contains(&self, sum: usize) -> bool {
+        sum <= self.bound && self.words[sum / 64] >> (sum % 64) & 1 == 1
+    }
+
+    /// Adds every sum of other plus shift that is within the bound.
+    fn add_shifted(&mut self, other: &Sums, shift: usize) {
+        let (word_shift, bit_shift) = (shift / 64, shift % 64);
+
+        for index in word_shift..self.words.len() {
+            let source = index - word_shift;
+            let mut word = other.words[source] << bit_shift;
+            if bit_shift > 0 && source > 0 {
+                word |= other.words[source - 1] >> (64 - bit_shift);
+            }
+
+            self.words[index] |= word;
+        }
+
+        // Drop the sums past the bound in the last word.
+        let last = self.words.len() - 1;
+        self.words[last] &= u64::MAX >> (63 - self.bound % 64);
+    }
+
+    /// Iterates over the sums in increasing order, skipping empty words.
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(index, word)| {
+            let mut word = *word;
+            iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+
+                Some(index * 64 + bit)
+            })
+        })
+    }
+}
+
+/// Chooses directories to delete, none inside another, over the directories sorted by path.
+///
+/// Sorted by path, every directory is followed by the directories inside it, so the directories
+/// form a preorder and deleting one skips to the end of its subtree. The sums reachable from a
+/// position are then the sums reachable from the next position, plus the directory's size on top
+/// of the sums reachable from the end of its subtree. Only sums up to a bound are kept, and only
+/// the few sets that later positions still need.
+struct CleanupPlanner {
+    sizes: Vec<usize>,
+    /// The position right after the subtree of each directory.
+    ends: Vec<usize>,
+    bound: usize,
+}
+
+impl CleanupPlanner {
+    fn new(paths: &[(String, u32)], bound: usize) -> Self {
+        let ends = (0..paths.len())
+            .map(|index| {
+                (index + 1..paths.len())
+                    .find(|next| !paths[*next].0.starts_with(&paths[index].0))
+                    .unwrap_or(paths.len())
+            })
+            .collect();
+
+        CleanupPlanner {
+            sizes: paths.iter().map(|(_, size)| *size as usize).collect(),
+            ends,
+            bound,
+        }
+    }
+
+    /// Finds the sums reachable from range.start, given the sums reachable from range.end.
+    fn sums_before(&self, range: Range<usize>, base: Sums) -> Sums {
+        // How many directories still need the sums at each position.
+        let mut needed = HashMap::<usize, usize>::new();
+        for index in range.clone() {
+            *needed.entry(self.ends[index]).or_default() += 1;
+        }
+
+        let mut saved = HashMap::new();
+        if needed.contains_key(&range.end) {
+            saved.insert(range.end, base.clone());
+        }
+
+        let mut current = base;
+        for index in range.rev() {
+            let end = self.ends[index];
+
+            let mut sums = current;
+            sums.add_shifted(&saved[&end], self.sizes[index]);
+
+            if let Some(count) = needed.get_mut(&end) {
+                *count -= 1;
+                if *count == 0 {
+                    needed.remove(&end);
+                    saved.remove(&end);
+                }
+            }
+            if needed.contains_key(&index) {
+                saved.insert(index, sums.clone());
+            }
+
+            current = sums;
+        }
+
+        current
+    }
+
+    /// Finds the sums reachable from the start of each subtree in a range of sibling subtrees,
+    /// and from the end of the range.
+    fn sibling_sums(&self, range: Range<usize>) -> Vec<(usize, Sums)> {
+        let mut starts = vec![range.start];
+        while let Some(last) = starts.last()
+            && *last < range.end
+        {
+            starts.push(self.ends[*last]);
+        }
+        starts.pop();
+
+        let mut siblings = vec![(range.end, Sums::zero(self.bound))];
+        for start in starts.into_iter().rev() {
+            let (end, sums) = &siblings[siblings.len() - 1];
+            let sums = self.sums_before(start..*end, sums.clone());
+            siblings.push((start, sums));
+        }
+        siblings.reverse();
+
+        siblings
+    }
+
+    /// Picks directories adding up to exactly target from a range of sibling subtrees.
+    ///
+    /// A subtree is skipped if the later siblings can reach the target without it, and deleted
+    /// whole if they can reach the rest. Otherwise the target is split between directories inside
+    /// the subtree and the later siblings.
+    fn pick(
+        &self,
+        siblings: &[(usize, Sums)],
+        mut target: usize,
+        plan: &mut Vec<usize>,
+    ) -> Result<()> {
+        for pair in siblings.windows(2) {
+            let (start, _) = pair[0];
+            let (end, rest) = &pair[1];
+            let size = self.sizes[start];
+
+            if rest.contains(target) {
+                continue;
+            }
+            if target >= size && rest.contains(target - size) {
+                plan.push(start);
+                target -= size;
+                continue;
+            }
+
+            let inner = self.sibling_sums(start + 1..*end);
+            let Some(later) = rest
+                .iter()
+                .find(|later| *later <= target && inner[0].1.contains(target - later))
+            else {
+                return Err(anyhow!("Cannot split {} between directories", target));
+            };
+
+            self.pick(&inner, target - later, plan)?;
+            target = later;
+        }
+
+        Ok(())
+    }
+}
+
+fn cleanup(input: String, fewest: bool, disk_space: u32, space_needed: u32) -> Result<String> {
+    let filesystem = parse_transcript(input)?;
+    let mut paths = dir_sizes(&filesystem)?.into_iter().collect::<Vec<_>>();
+    paths.sort();
+
+    let used = paths[0].1;
+    let Some(free) = disk_space.checked_sub(used) else {
+        return Err(anyhow!("Cannot fit {} in a disk of {}", used, disk_space));
+    };
+    let required = space_needed.saturating_sub(free);
+
+    let mut plan = Vec::new();
+    if required > 0 {
+        // Deleting the smallest directory that frees enough on its own is always a valid plan,
+        // and no plan deletes fewer directories.
+        let Some(smallest) = (0..paths.len())
+            .filter(|index| paths[*index].1 >= required)
+            .min_by_key(|index| paths[*index].1)
+        else {
+            return Err(anyhow!(
+                "Cannot free {} even by deleting everything",
+                required
+            ));
+        };
+
+        if fewest {
+            plan.push(smallest);
+        } else {
+            // No plan needs to delete more than that directory does.
+            let planner = CleanupPlanner::new(&paths, paths[smallest].1 as usize);
+            let siblings = planner.sibling_sums(0..paths.len());
+
+            let Some(best) = siblings[0].1.iter().find(|sum| *sum >= required as usize) else {
+                return Err(anyhow!("Cannot find a plan freeing {}", required));
+            };
+            planner.pick(&siblings, best, &mut plan)?;
+        }
+    }
+
+    let mut report = String::new();
+    for index in &plan {
+        writeln!(report, "Delete {} ({})", paths[*index].0, paths[*index].1)?;
+    }
+    let deleted = plan.iter().map(|index| paths[*index].1).sum::<u32>();
+    writeln!(report, "Deleted: {deleted} in {} directories", plan.len())?;
+    writeln!(
+        report,
+        "Free space: {free} before, {} after, {space_needed} needed",
+        free + deleted
+    )?;
+
+    Ok(report)
+}
+
+fn materialise(input: String, target: &Path) -> Result<String> {
+    let filesystem = parse_transcript(input)?;
+
+    if target.exists() && fs::read_dir(target)?.next().is_some() {
+        return Err(anyhow!(
+            "Cannot materialise into non-empty directory: {}",
+            target.display()
+        ));
+    }
+    fs::create_dir_all(target)?;
+
+    // Parents come before their children, so every directory exists before its contents.
+    let (mut dir_count, mut file_count) = (0, 0);
+    for (index, node) in filesystem.nodes.iter().enumerate().skip(1) {
+        if node.name.is_empty() || node.name == "." || node.name == ".." || node.name.contains("/")
+        {
+            return Err(anyhow!("Cannot materialise name: {:?}", node.name));
+        }
+
+        let path = target.join(filesystem.path(index).trim_start_matches("/"));
+        match node.kind {
+            NodeKind::Dir(_) => {
+                fs::create_dir(path)?;
+                dir_count += 1;
+            }
+            NodeKind::File(size) => {
+                // Setting the length without writing leaves a sparse file on most filesystems.
+                File::create(path)?.set_len(size as u64)?;
+                file_count += 1;
+            }
+        }
+    }
+
+    let mut report = String::new();
+    writeln!(
+        report,
+        "Created {dir_count} directories and {file_count} files under {}",
+        target.display()
+    )?;
+
+    let computed = dir_sizes(&filesystem)?;
+    let mut on_disk = HashMap::new();
+    walk_dir_sizes(target, String::from("/"), &mut on_disk)?;
+
+    let paths = computed
+        .keys()
+        .chain(on_disk.keys())
+        .collect::<BTreeSet<_>>();
+    let mismatches = paths
+        .iter()
+        .filter(|path| {
+            computed.get(**path).map(|size| *size as u64) != on_disk.get(**path).copied()
+        })
+        .map(|path| {
+            let describe =
+                |size: Option<u64>| size.map_or(String::from("missing"), |size| size.to_string());
+            format!(
+                "{path}: computed {}, on disk {}",
+                describe(computed.get(*path).map(|size| *size as u64)),
+                describe(on_disk.get(*path).copied())
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if mismatches.is_empty() {
+        writeln!(
+            report,
+            "Checked {} directories: all sizes match",
+            paths.len()
+        )?;
+    } else {
+        writeln!(
+            report,
+            "Checked {} directories: {} sizes differ",
+            paths.len(),
+            mismatches.len()
+        )?;
+        for mismatch in mismatches {
+            writeln!(report, "{mismatch}")?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Adds up the apparent sizes of the files under a directory on disk, recording the size of every
+/// directory by its path in the transcript format.
+fn walk_dir_sizes(dir: &Path, path: String, sizes: &mut HashMap<String, u64>) -> Result<u64> {
+    let mut size = 0;
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if entry.file_type()?.is_dir() {
+            size += walk_dir_sizes(&entry.path(), format!("{path}{name}/"), sizes)?;
+        } else {
+            size += entry.metadata()?.len();
+        }
+    }
+
+    sizes.insert(path, size);
+
+    Ok(size)
 }
 
 #[cfg(test)]
@@ -170,4 +1039,197 @@ $ ls
 
         Ok(())
     }
+
+    #[test]
+    fn tree_view() -> Result<()> {
+        assert_eq!(
+            show(EXAMPLE_INPUT.trim().to_string(), View::Tree)?,
+            [
+                "/",
+                "├── a",
+                "│   ├── e",
+                "│   │   └── i (584)",
+                "│   ├── f (29116)",
+                "│   ├── g (2557)",
+                "│   └── h.lst (62596)",
+                "├── b.txt (14848514)",
+                "├── c.dat (8504156)",
+                "└── d",
+                "    ├── d.ext (5626152)",
+                "    ├── d.log (8033020)",
+                "    ├── j (4060174)",
+                "    └── k (7214296)",
+                "",
+            ]
+            .join("\n")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn du_view() -> Result<()> {
+        assert_eq!(
+            show(EXAMPLE_INPUT.trim().to_string(), View::Du)?,
+            "584\t/a/e\n93K\t/a\n24M\t/d\n46M\t/\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sizes_by_path() -> Result<()> {
+        let input = || EXAMPLE_INPUT.trim().to_string();
+
+        assert_eq!(path_size(input(), "/a/e")?, 584);
+        assert_eq!(path_size(input(), "/a/e/")?, 584);
+        assert_eq!(path_size(input(), "/d/k")?, 7214296);
+        assert_eq!(path_size(input(), "/")?, 48381165);
+        assert!(path_size(input(), "/x").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_export() -> Result<()> {
+        let json = show(EXAMPLE_INPUT.trim().to_string(), View::Json)?;
+
+        assert!(json.starts_with(
+            "{\"name\":\"/\",\"type\":\"dir\",\"size\":48381165,\"children\":[{\"name\":\"a\""
+        ));
+        assert!(json.contains("{\"name\":\"i\",\"type\":\"file\",\"size\":584}"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn repeated_listing() -> Result<()> {
+        let input = EXAMPLE_INPUT.trim().to_string()
+            + "\n$ cd /\n$ ls\n8504156 c.dat\ndir a\ndir d\n14848514 b.txt";
+
+        assert_eq!(part_1(input)?, 95437);
+
+        Ok(())
+    }
+
+    #[test]
+    fn contradicting_listing() {
+        let input = EXAMPLE_INPUT.trim().to_string()
+            + "\n$ cd /\n$ ls\ndir a\n14848515 b.txt\ndir d\ndir x";
+
+        assert_eq!(
+            check(input),
+            "Line 25: listing of / contradicts the listing on line 2: \
+             b.txt was a file of 14848514, now a file of 14848515; c.dat is missing; \
+             x is new, a directory\n"
+        );
+    }
+
+    #[test]
+    fn cd_before_listing() -> Result<()> {
+        // Entering a and e before listing the root and a is fine once they are all listed.
+        let input = "$ cd /\n$ cd a\n$ cd e\n$ ls\n584 i\n$ cd ..\n$ ls\ndir e\n29116 f\n$ cd /\n$ ls\ndir a";
+        assert_eq!(path_size(String::from(input), "/")?, 29700);
+
+        assert_eq!(
+            check(String::from(
+                "$ cd /\n$ cd a\n$ ls\n10 f\n$ cd ..\n$ cd b\n"
+            )),
+            "Line 1: / is never listed, so its size is unknown\n\
+             Line 6: /b/ is never listed, so its size is unknown\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bad_lines() {
+        assert_eq!(
+            check(String::from("$ cd ..\n$ ls\n10 f\nhello\n$ pwd\n$ cd f")),
+            "Line 1: cannot cd above the root\n\
+             Line 4: cannot parse output: hello\n\
+             Line 5: cannot parse command: $ pwd\n\
+             Line 6: cannot cd into file /f\n"
+        );
+        assert!(part_1(String::from("$ ls\n10 f\n10 f")).is_err());
+    }
+
+    const NESTED_INPUT: &str = r"
+$ cd /
+$ ls
+dir x
+dir y
+dir z
+$ cd x
+$ ls
+60 a
+$ cd ..
+$ cd y
+$ ls
+50 b
+$ cd ..
+$ cd z
+$ ls
+15 c
+dir w
+$ cd w
+$ ls
+30 d
+";
+
+    #[test]
+    fn cleanup_least_data() -> Result<()> {
+        // 45 is free, so 80 more is needed: y and w add up to exactly that.
+        assert_eq!(
+            cleanup(NESTED_INPUT.trim().to_string(), false, 200, 125)?,
+            "Delete /y/ (50)\nDelete /z/w/ (30)\nDeleted: 80 in 2 directories\n\
+             Free space: 45 before, 125 after, 125 needed\n"
+        );
+        assert_eq!(
+            cleanup(
+                EXAMPLE_INPUT.trim().to_string(),
+                false,
+                DISK_SPACE,
+                SPACE_NEEDED
+            )?,
+            "Delete /d/ (24933642)\nDeleted: 24933642 in 1 directories\n\
+             Free space: 21618835 before, 46552477 after, 30000000 needed\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn cleanup_fewest() -> Result<()> {
+        assert_eq!(
+            cleanup(NESTED_INPUT.trim().to_string(), true, 200, 125)?,
+            "Delete / (155)\nDeleted: 155 in 1 directories\n\
+             Free space: 45 before, 200 after, 125 needed\n"
+        );
+        assert!(cleanup(NESTED_INPUT.trim().to_string(), false, 200, 201).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn materialise_example() -> Result<()> {
+        let target = std::env::temp_dir().join(format!("day-7-materialise-{}", std::process::id()));
+
+        let report = materialise(EXAMPLE_INPUT.trim().to_string(), &target);
+        let again = materialise(EXAMPLE_INPUT.trim().to_string(), &target);
+        let size = fs::metadata(target.join("d/k"))?.len();
+        fs::remove_dir_all(&target)?;
+
+        assert_eq!(
+            report?,
+            format!(
+                "Created 3 directories and 10 files under {}\nChecked 4 directories: all sizes match\n",
+                target.display()
+            )
+        );
+        assert!(again.is_err());
+        assert_eq!(size, 7214296);
+
+        Ok(())
+    }
 }
@@ -1,11 +1,89 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
 use anyhow::{Result, anyhow};
+use clap::{Subcommand, ValueEnum};
+
+use cli::{CountingAllocator, Part, Task, get_task, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Writes images of the tree heights, the visible trees and the scenic scores, marking the
+    /// best tree and its lines of sight.
+    Export {
+        /// Directory to write heights, visible and scenic images into.
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+
+        #[arg(short, long, default_value = "png")]
+        format: ImageFormat,
+
+        /// Width and height of each tree in pixels.
+        #[arg(short, long, default_value_t = 8)]
+        scale: usize,
+    },
+    /// Draws the trees seen from a tree, or from a point outside the grid.
+    Look {
+        /// Row of the point, counting from 0. Rows above the grid are negative.
+        #[arg(short, long, allow_negative_numbers = true)]
+        row: isize,
+
+        /// Column of the point, counting from 0. Columns left of the grid are negative.
+        #[arg(short, long, allow_negative_numbers = true)]
+        col: isize,
+
+        /// Eye height when looking from a tree. Defaults to the tree's height.
+        #[arg(short, long)]
+        eye: Option<u8>,
+
+        /// Also looks along the diagonals.
+        #[arg(short, long)]
+        diagonals: bool,
+    },
+}
 
-use cli::{Part, get_part};
+#[derive(Clone, Copy, ValueEnum)]
+enum ImageFormat {
+    Png,
+    /// Binary PPM, readable without any image library.
+    Ppm,
+}
 
 fn main() {
-    match get_part("inputs/day-8.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task::<Mode>("inputs/day-8.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(
+            Mode::Export {
+                output,
+                format,
+                scale,
+            },
+            input,
+        )) => match export(input, &output, format, scale) {
+            Ok(paths) => paths.iter().for_each(|path| println!("{}", path.display())),
+            Err(error) => println!("{error:?}"),
+        },
+        Ok(Task::Mode(
+            Mode::Look {
+                row,
+                col,
+                eye,
+                diagonals,
+            },
+            input,
+        )) => match look(input, (row, col), eye, diagonals) {
+            Ok(view) => print!("{view}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
@@ -29,125 +107,558 @@ fn convert_input_into_grid(input: String) -> Vec<Vec<u8>> {
         .collect()
 }
 
-/// Transposes the square grid from rows into columns, and vice versa.
-fn transpose(grid: &[Vec<u8>]) -> Result<Vec<Vec<u8>>> {
-    let grid_size = grid.len();
+/// What each tree sees, from [survey].
+struct Survey {
+    /// Whether the tree can be seen from outside the grid.
+    visible: Vec<Vec<bool>>,
+    scenic_scores: Vec<Vec<usize>>,
+}
 
-    let mut transpose: Vec<Vec<u8>> = Vec::new();
-    grid.iter().try_for_each(|row| {
-        if row.len() != grid_size {
-            return Err(anyhow!("Grid is not a square"));
-        }
+/// Gets the number of rows and columns, checking that every row is as long as the first.
+fn grid_size(grid: &[Vec<u8>]) -> Result<(usize, usize)> {
+    let cols = grid.first().map_or(0, Vec::len);
+    if grid.iter().any(|row| row.len() != cols) {
+        return Err(anyhow!("Grid is not a rectangle"));
+    }
 
-        transpose.push(vec![0; grid_size]);
+    Ok((grid.len(), cols))
+}
 
-        Ok(())
-    })?;
+fn count_visible_trees(grid: &[Vec<u8>]) -> Result<usize> {
+    Ok(survey(grid)?
+        .visible
+        .iter()
+        .flatten()
+        .filter(|visible| **visible)
+        .count())
+}
+
+fn max_scenic_score(grid: &[Vec<u8>]) -> Result<usize> {
+    Ok(survey(grid)?
+        .scenic_scores
+        .iter()
+        .flatten()
+        .max()
+        .copied()
+        .unwrap_or(0))
+}
 
-    for (row, bytes) in grid.iter().enumerate() {
-        for (col, byte) in bytes.iter().enumerate() {
-            transpose[col][row] = *byte;
+/// Finds which trees are visible and their scenic scores with one sweep per line and direction.
+fn survey(grid: &[Vec<u8>]) -> Result<Survey> {
+    let (rows, cols) = grid_size(grid)?;
+
+    let mut visible = vec![vec![false; cols]; rows];
+    let mut scenic_scores = vec![vec![1; cols]; rows];
+    let mut stack = Vec::new();
+    let height = |row: usize, col: usize| grid[row][col];
+    let mut record = |row: usize, col: usize, distance: usize, sees_edge: bool| {
+        visible[row][col] |= sees_edge;
+        scenic_scores[row][col] *= distance;
+    };
+
+    for row in 0..rows {
+        // Looking west, then east.
+        look_back(
+            &mut stack,
+            cols,
+            |col| height(row, col),
+            |col, distance, edge| record(row, col, distance, edge),
+        );
+        look_back(
+            &mut stack,
+            cols,
+            |col| height(row, cols - 1 - col),
+            |col, distance, edge| record(row, cols - 1 - col, distance, edge),
+        );
+    }
+    for col in 0..cols {
+        // Looking north, then south.
+        look_back(
+            &mut stack,
+            rows,
+            |row| height(row, col),
+            |row, distance, edge| record(row, col, distance, edge),
+        );
+        look_back(
+            &mut stack,
+            rows,
+            |row| height(rows - 1 - row, col),
+            |row, distance, edge| record(rows - 1 - row, col, distance, edge),
+        );
+    }
+
+    Ok(Survey {
+        visible,
+        scenic_scores,
+    })
+}
+
+/// Looks back along a line of trees from each tree in turn, recording how many trees it can see
+/// and whether it can see past all of them to the edge.
+///
+/// The stack holds the trees that are taller than every tree after them so far. A tree pops the
+/// shorter ones, since they can never block a later view past it, and the tree left on top is the
+/// nearest one at least as tall, which blocks its view.
+fn look_back(
+    stack: &mut Vec<(usize, u8)>,
+    len: usize,
+    height: impl Fn(usize) -> u8,
+    mut record: impl FnMut(usize, usize, bool),
+) {
+    stack.clear();
+
+    for position in 0..len {
+        let tree = height(position);
+        while stack.last().is_some_and(|(_, other)| *other < tree) {
+            stack.pop();
+        }
+
+        match stack.last() {
+            Some((blocker, _)) => record(position, position - blocker, false),
+            None => record(position, position, true),
         }
+        stack.push((position, tree));
     }
+}
+
+type Colour = [u8; 3];
+
+/// Colours from low to high values, roughly those of the viridis colour map.
+const COLOUR_SCALE: [Colour; 5] = [
+    [68, 1, 84],
+    [59, 82, 139],
+    [33, 145, 140],
+    [94, 201, 98],
+    [253, 231, 37],
+];
+const HIDDEN: Colour = [40, 40, 40];
+const BEST_TREE: Colour = [255, 0, 0];
+const LINE_OF_SIGHT: Colour = [255, 255, 255];
+
+/// Picks the colour of a value from 0.0 to 1.0 on [COLOUR_SCALE].
+fn colour_scale(value: f64) -> Colour {
+    let position = value.clamp(0.0, 1.0) * (COLOUR_SCALE.len() - 1) as f64;
+    let index = (position as usize).min(COLOUR_SCALE.len() - 2);
+    let fraction = position - index as f64;
+
+    blend(COLOUR_SCALE[index], COLOUR_SCALE[index + 1], fraction)
+}
 
-    Ok(transpose)
+fn blend(from: Colour, to: Colour, fraction: f64) -> Colour {
+    [0, 1, 2].map(|channel| {
+        (from[channel] as f64 + (to[channel] as f64 - from[channel] as f64) * fraction).round()
+            as u8
+    })
 }
 
-fn count_visible_trees(grid_by_rows: &[Vec<u8>]) -> Result<usize> {
-    let grid_size = grid_by_rows.len();
+struct Image {
+    width: usize,
+    height: usize,
+    /// Pixels row by row.
+    pixels: Vec<Colour>,
+}
 
-    let grid_by_cols = transpose(grid_by_rows)?;
+impl Image {
+    /// Draws each tree as a square of scale by scale pixels.
+    fn from_grid(
+        rows: usize,
+        cols: usize,
+        scale: usize,
+        colour: impl Fn(usize, usize) -> Colour,
+    ) -> Self {
+        let (width, height) = (cols * scale, rows * scale);
+
+        Image {
+            width,
+            height,
+            pixels: (0..height * width)
+                .map(|pixel| colour(pixel / width / scale, pixel % width / scale))
+                .collect(),
+        }
+    }
 
-    let mut visible_count = 0;
-    for (row, trees) in grid_by_rows.iter().enumerate() {
-        for (col, tree) in trees.iter().enumerate() {
-            if row == 0 || row == grid_size - 1 || col == 0 || col == grid_size - 1 {
-                visible_count += 1;
-                continue;
-            }
+    fn write(&self, path: &Path, format: ImageFormat) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let bytes = self.pixels.concat();
 
-            if is_visible(
-                *tree,
-                &grid_by_cols[col][..row],
-                &grid_by_rows[row][(col + 1)..],
-                &grid_by_cols[col][(row + 1)..],
-                &grid_by_rows[row][..col],
-            ) {
-                visible_count += 1;
+        match format {
+            ImageFormat::Png => {
+                let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+                encoder.set_color(png::ColorType::Rgb);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.write_header()?.write_image_data(&bytes)?;
+            }
+            ImageFormat::Ppm => {
+                write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+                writer.write_all(&bytes)?;
             }
         }
+
+        Ok(())
     }
+}
+
+const CARDINAL: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+const DIAGONAL: [(isize, isize); 4] = [(-1, 1), (1, 1), (1, -1), (-1, -1)];
 
-    Ok(visible_count)
+type Trees = BTreeSet<(usize, usize)>;
+
+fn directions(diagonals: bool) -> impl Iterator<Item = (isize, isize)> {
+    CARDINAL
+        .into_iter()
+        .chain(DIAGONAL.into_iter().filter(move |_| diagonals))
 }
 
-fn is_visible(height: u8, n: &[u8], e: &[u8], s: &[u8], w: &[u8]) -> bool {
-    n.iter().all(|tree| *tree < height)
-        || e.iter().all(|tree| *tree < height)
-        || s.iter().all(|tree| *tree < height)
-        || w.iter().all(|tree| *tree < height)
+/// Walks from a point in one direction, yielding the trees on the way in order.
+fn ray(
+    grid: &[Vec<u8>],
+    (row, col): (isize, isize),
+    (row_step, col_step): (isize, isize),
+) -> impl Iterator<Item = (usize, usize)> {
+    let (rows, cols) = (grid.len(), grid.first().map_or(0, Vec::len));
+
+    // Step counts are i128, as a point far outside the grid can be more than isize::MAX steps
+    // from it.
+    let (row_steps, col_steps) = (
+        steps_inside(row, row_step, rows),
+        steps_inside(col, col_step, cols),
+    );
+    let first = 1.max(*row_steps.start()).max(*col_steps.start());
+    let last = (*row_steps.end()).min(*col_steps.end());
+
+    (first..=last).map(move |step| {
+        (
+            (row as i128 + row_step as i128 * step) as usize,
+            (col as i128 + col_step as i128 * step) as usize,
+        )
+    })
 }
 
-fn max_scenic_score(grid_by_rows: &[Vec<u8>]) -> Result<usize> {
-    let grid_by_cols = transpose(grid_by_rows)?;
+/// Finds the step counts that keep a position moved by a unit step inside 0..size, an empty range
+/// if there are none.
+fn steps_inside(position: isize, step: isize, size: usize) -> RangeInclusive<i128> {
+    let (position, last) = (position as i128, size as i128 - 1);
 
-    let mut max_scenic_score = 0;
-    for (row, trees) in grid_by_rows.iter().enumerate() {
-        for (col, tree) in trees.iter().enumerate() {
-            // Including visible trees.
+    match step.signum() {
+        1 => -position..=last - position,
+        -1 => position - last..=position,
+        _ if (0..=last).contains(&position) => i128::MIN..=i128::MAX,
+        _ => RangeInclusive::new(1, 0),
+    }
+}
 
-            let scenic_score = scenic_score(
-                *tree,
-                &grid_by_cols[col][..row],
-                &grid_by_rows[row][(col + 1)..],
-                &grid_by_cols[col][(row + 1)..],
-                &grid_by_rows[row][..col],
-            );
-            if scenic_score > max_scenic_score {
-                max_scenic_score = scenic_score;
+/// Finds the trees seen from a tree at an eye height, looking along each ray until a tree at
+/// least as tall as the eye height blocks the view. The blocking tree is seen too.
+fn seen_from_tree(
+    grid: &[Vec<u8>],
+    (row, col): (usize, usize),
+    eye: u8,
+    diagonals: bool,
+) -> Result<Trees> {
+    let (rows, cols) = grid_size(grid)?;
+    if row >= rows || col >= cols {
+        return Err(anyhow!(
+            "Cannot look from outside the grid: ({}, {})",
+            row,
+            col
+        ));
+    }
+
+    let mut seen = BTreeSet::new();
+    for direction in directions(diagonals) {
+        for (r, c) in ray(grid, (row as isize, col as isize), direction) {
+            seen.insert((r, c));
+            if grid[r][c] >= eye {
+                break;
             }
         }
     }
 
-    Ok(max_scenic_score)
-}
-
-fn scenic_score(height: u8, n: &[u8], e: &[u8], s: &[u8], w: &[u8]) -> usize {
-    direction_scenic_score(height, n.iter().rev())
-        * direction_scenic_score(height, e.iter())
-        * direction_scenic_score(height, s.iter())
-        * direction_scenic_score(height, w.iter().rev())
+    Ok(seen)
 }
 
-/// Counts the number of trees before view is blocked (count includes the blocking tree).
+/// Finds the trees visible from a point outside the grid, looking along each ray into it.
 ///
-/// Note the puzzle is worded ambiguously -- your view of a shorter tree behind a taller tree is not
-/// blocked -- just so long as the taller tree does not block your view entirely.
-fn direction_scenic_score<'a, T>(height: u8, trees_iter: T) -> usize
-where
-    T: Iterator<Item = &'a u8>,
-{
-    trees_iter
-        .fold((0usize, true), |acc, tree| {
-            let (count, to_continue) = acc;
-
-            if !to_continue {
-                return acc;
+/// As from the edges in part 1, a tree is visible if every tree in front of it is shorter.
+fn seen_from_outside(
+    grid: &[Vec<u8>],
+    (row, col): (isize, isize),
+    diagonals: bool,
+) -> Result<Trees> {
+    let (rows, cols) = grid_size(grid)?;
+    if (0..rows as isize).contains(&row) && (0..cols as isize).contains(&col) {
+        return Err(anyhow!(
+            "Cannot look from inside the grid: ({}, {})",
+            row,
+            col
+        ));
+    }
+
+    let mut seen = BTreeSet::new();
+    for direction in directions(diagonals) {
+        let mut tallest = None;
+        for (r, c) in ray(grid, (row, col), direction) {
+            if tallest.is_none_or(|tallest| grid[r][c] > tallest) {
+                seen.insert((r, c));
+                tallest = Some(grid[r][c]);
             }
+        }
+    }
 
-            if *tree >= height {
-                (count + 1, false)
+    Ok(seen)
+}
+
+/// Answers a line-of-sight query, from a tree if the point is inside the grid or from a vantage
+/// point otherwise, and draws the trees seen.
+fn look(
+    input: String,
+    (row, col): (isize, isize),
+    eye: Option<u8>,
+    diagonals: bool,
+) -> Result<String> {
+    let grid = convert_input_into_grid(input);
+    let (rows, cols) = grid_size(&grid)?;
+
+    let inside = (0..rows as isize).contains(&row) && (0..cols as isize).contains(&col);
+    let seen = if inside {
+        let (row, col) = (row as usize, col as usize);
+        seen_from_tree(&grid, (row, col), eye.unwrap_or(grid[row][col]), diagonals)?
+    } else {
+        seen_from_outside(&grid, (row, col), diagonals)?
+    };
+
+    let mut view = format!("Sees {} trees\n", seen.len());
+    for (r, trees) in grid.iter().enumerate() {
+        for (c, tree) in trees.iter().enumerate() {
+            view.push(if inside && (r as isize, c as isize) == (row, col) {
+                '@'
+            } else if seen.contains(&(r, c)) {
+                (b'0' + tree) as char
             } else {
-                (count + 1, true)
-            }
+                '.'
+            });
+        }
+        view.push('\n');
+    }
+
+    Ok(view)
+}
+
+fn export(input: String, output: &Path, format: ImageFormat, scale: usize) -> Result<Vec<PathBuf>> {
+    if scale == 0 {
+        return Err(anyhow!("Invalid scale: 0"));
+    }
+
+    let grid = convert_input_into_grid(input);
+    let (rows, cols) = grid_size(&grid)?;
+    let survey = survey(&grid)?;
+
+    let Some((best_row, best_col)) = (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .max_by_key(|(row, col)| survey.scenic_scores[*row][*col])
+    else {
+        return Err(anyhow!("Cannot export an empty grid"));
+    };
+    let max_score = survey.scenic_scores[best_row][best_col].max(1);
+    let sight = seen_from_tree(&grid, (best_row, best_col), grid[best_row][best_col], false)?;
+
+    // Marks the best tree in red, and tints the trees it can see.
+    let mark = |row: usize, col: usize, colour: Colour| {
+        if (row, col) == (best_row, best_col) {
+            BEST_TREE
+        } else if sight.contains(&(row, col)) {
+            blend(colour, LINE_OF_SIGHT, 0.6)
+        } else {
+            colour
+        }
+    };
+    let height_colour = |row: usize, col: usize| colour_scale(grid[row][col] as f64 / 9.0);
+
+    let images = [
+        (
+            "heights",
+            Image::from_grid(rows, cols, scale, |row, col| {
+                mark(row, col, height_colour(row, col))
+            }),
+        ),
+        (
+            "visible",
+            Image::from_grid(rows, cols, scale, |row, col| {
+                let colour = if survey.visible[row][col] {
+                    height_colour(row, col)
+                } else {
+                    HIDDEN
+                };
+                mark(row, col, colour)
+            }),
+        ),
+        (
+            "scenic",
+            Image::from_grid(rows, cols, scale, |row, col| {
+                // Scores grow multiplicatively, so a square root spreads the many low ones out.
+                let score = survey.scenic_scores[row][col] as f64 / max_score as f64;
+                mark(row, col, colour_scale(score.sqrt()))
+            }),
+        ),
+    ];
+
+    fs::create_dir_all(output)?;
+    let extension = match format {
+        ImageFormat::Png => "png",
+        ImageFormat::Ppm => "ppm",
+    };
+
+    images
+        .iter()
+        .map(|(name, image)| {
+            let path = output.join(format!("{name}.{extension}"));
+            image.write(&path, format)?;
+
+            Ok(path)
         })
-        .0
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// The original solution, which slices out the full row and column of every tree.
+    mod slicing {
+        use super::*;
+
+        /// Transposes the square grid from rows into columns, and vice versa.
+        pub fn transpose(grid: &[Vec<u8>]) -> Result<Vec<Vec<u8>>> {
+            let grid_size = grid.len();
+
+            let mut transpose: Vec<Vec<u8>> = Vec::new();
+            grid.iter().try_for_each(|row| {
+                if row.len() != grid_size {
+                    return Err(anyhow!("Grid is not a square"));
+                }
+
+                transpose.push(vec![0; grid_size]);
+
+                Ok(())
+            })?;
+
+            for (row, bytes) in grid.iter().enumerate() {
+                for (col, byte) in bytes.iter().enumerate() {
+                    transpose[col][row] = *byte;
+                }
+            }
+
+            Ok(transpose)
+        }
+
+        pub fn count_visible_trees(grid_by_rows: &[Vec<u8>]) -> Result<usize> {
+            let grid_size = grid_by_rows.len();
+
+            let grid_by_cols = transpose(grid_by_rows)?;
+
+            let mut visible_count = 0;
+            for (row, trees) in grid_by_rows.iter().enumerate() {
+                for (col, tree) in trees.iter().enumerate() {
+                    if row == 0 || row == grid_size - 1 || col == 0 || col == grid_size - 1 {
+                        visible_count += 1;
+                        continue;
+                    }
+
+                    if is_visible(
+                        *tree,
+                        &grid_by_cols[col][..row],
+                        &grid_by_rows[row][(col + 1)..],
+                        &grid_by_cols[col][(row + 1)..],
+                        &grid_by_rows[row][..col],
+                    ) {
+                        visible_count += 1;
+                    }
+                }
+            }
+
+            Ok(visible_count)
+        }
+
+        fn is_visible(height: u8, n: &[u8], e: &[u8], s: &[u8], w: &[u8]) -> bool {
+            n.iter().all(|tree| *tree < height)
+                || e.iter().all(|tree| *tree < height)
+                || s.iter().all(|tree| *tree < height)
+                || w.iter().all(|tree| *tree < height)
+        }
+
+        pub fn max_scenic_score(grid_by_rows: &[Vec<u8>]) -> Result<usize> {
+            let grid_by_cols = transpose(grid_by_rows)?;
+
+            let mut max_scenic_score = 0;
+            for (row, trees) in grid_by_rows.iter().enumerate() {
+                for (col, tree) in trees.iter().enumerate() {
+                    // Including visible trees.
+
+                    let scenic_score = scenic_score(
+                        *tree,
+                        &grid_by_cols[col][..row],
+                        &grid_by_rows[row][(col + 1)..],
+                        &grid_by_cols[col][(row + 1)..],
+                        &grid_by_rows[row][..col],
+                    );
+                    if scenic_score > max_scenic_score {
+                        max_scenic_score = scenic_score;
+                    }
+                }
+            }
+
+            Ok(max_scenic_score)
+        }
+
+        fn scenic_score(height: u8, n: &[u8], e: &[u8], s: &[u8], w: &[u8]) -> usize {
+            direction_scenic_score(height, n.iter().rev())
+                * direction_scenic_score(height, e.iter())
+                * direction_scenic_score(height, s.iter())
+                * direction_scenic_score(height, w.iter().rev())
+        }
+
+        /// Counts the number of trees before view is blocked (count includes the blocking tree).
+        ///
+        /// Note the puzzle is worded ambiguously -- your view of a shorter tree behind a taller tree is not
+        /// blocked -- just so long as the taller tree does not block your view entirely.
+        pub fn direction_scenic_score<'a, T>(height: u8, trees_iter: T) -> usize
+        where
+            T: Iterator<Item = &'a u8>,
+        {
+            trees_iter
+                .fold((0usize, true), |acc, tree| {
+                    let (count, to_continue) = acc;
+
+                    if !to_continue {
+                        return acc;
+                    }
+
+                    if *tree >= height {
+                        (count + 1, false)
+                    } else {
+                        (count + 1, true)
+                    }
+                })
+                .0
+        }
+    }
+
+    /// Generates a square grid of random heights.
+    fn generate_grid(size: usize, seed: u64) -> Vec<Vec<u8>> {
+        // xorshift64, so the grid is the same on every run.
+        let mut state = seed;
+        let mut random = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 10) as u8
+        };
+
+        (0..size)
+            .map(|_| (0..size).map(|_| random()).collect())
+            .collect()
+    }
+
     const EXAMPLE_INPUT: &str = r"
 30373
 25512
@@ -169,4 +680,126 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn matches_slicing() -> Result<()> {
+        for (size, seed) in [(1, 1), (2, 2), (7, 3), (40, 4), (99, 5)] {
+            let grid = generate_grid(size, seed);
+
+            assert_eq!(
+                count_visible_trees(&grid)?,
+                slicing::count_visible_trees(&grid)?
+            );
+            assert_eq!(max_scenic_score(&grid)?, slicing::max_scenic_score(&grid)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn rectangular_grid() -> Result<()> {
+        let grid = convert_input_into_grid(String::from("30373\n25512\n65332"));
+
+        assert_eq!(count_visible_trees(&grid)?, 14);
+        assert_eq!(max_scenic_score(&grid)?, 2);
+        assert!(count_visible_trees(&convert_input_into_grid(String::from("303\n25"))).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn seen_from_best_tree() -> Result<()> {
+        let grid = convert_input_into_grid(EXAMPLE_INPUT.trim().to_string());
+
+        assert_eq!(
+            seen_from_tree(&grid, (3, 2), 5, false)?,
+            Trees::from([(1, 2), (2, 2), (3, 0), (3, 1), (3, 3), (3, 4), (4, 2)])
+        );
+        assert_eq!(
+            seen_from_tree(&grid, (3, 2), 5, true)?,
+            Trees::from([
+                (1, 2),
+                (1, 4),
+                (2, 1),
+                (2, 2),
+                (2, 3),
+                (3, 0),
+                (3, 1),
+                (3, 3),
+                (3, 4),
+                (4, 1),
+                (4, 2),
+                (4, 3)
+            ])
+        );
+        assert_eq!(seen_from_tree(&grid, (3, 2), 0, false)?.len(), 4);
+        assert!(seen_from_tree(&grid, (5, 0), 5, false).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn seen_from_vantage_point() -> Result<()> {
+        let grid = convert_input_into_grid(EXAMPLE_INPUT.trim().to_string());
+
+        // Above the middle column, 3 5 3 5 3 going down, only the down ray reaches the grid.
+        assert_eq!(
+            seen_from_outside(&grid, (-1, 2), false)?,
+            Trees::from([(0, 2), (1, 2)])
+        );
+        // The diagonals add the 7 down to the right, and the 0 then the 2 down to the left.
+        assert_eq!(seen_from_outside(&grid, (-1, 2), true)?.len(), 5);
+        assert!(seen_from_outside(&grid, (0, 0), false).is_err());
+
+        // Points far away see along the rays that reach the grid, without walking the distance.
+        assert_eq!(
+            seen_from_outside(&grid, (-100_000_000_000, 2), true)?,
+            Trees::from([(0, 2), (1, 2)])
+        );
+        assert_eq!(
+            seen_from_outside(&grid, (isize::MIN, isize::MIN), true)?,
+            Trees::from([(0, 0), (1, 1)])
+        );
+        assert_eq!(
+            seen_from_outside(&grid, (isize::MAX, 2), false)?,
+            Trees::from([(4, 2), (3, 2)])
+        );
+
+        // Seen from all around, the outside matches part 1.
+        let mut visible = Trees::new();
+        for index in 0..5 {
+            for point in [(-1, index), (5, index), (index, -1), (index, 5)] {
+                visible.extend(seen_from_outside(&grid, point, false)?);
+            }
+        }
+        assert_eq!(visible.len(), 21);
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_images() -> Result<()> {
+        let output = std::env::temp_dir().join(format!("day-8-export-{}", std::process::id()));
+
+        let paths = export(
+            EXAMPLE_INPUT.trim().to_string(),
+            &output,
+            ImageFormat::Ppm,
+            2,
+        );
+        let heights = fs::read(output.join("heights.ppm"));
+        fs::remove_dir_all(&output)?;
+
+        assert_eq!(paths?.len(), 3);
+        let heights = heights?;
+        let header = b"P6\n10 10\n255\n";
+        assert_eq!(&heights[..header.len()], header);
+        assert_eq!(heights.len(), header.len() + 10 * 10 * 3);
+
+        // The best tree is at row 3, column 2, so pixel (6, 4) is red.
+        let pixel = header.len() + (6 * 10 + 4) * 3;
+        assert_eq!(heights[pixel..pixel + 3], BEST_TREE);
+
+        Ok(())
+    }
 }
@@ -1,159 +1,172 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, fmt::Write};
 
 use anyhow::{Result, anyhow};
+use clap::Subcommand;
 
-use cli::{Part, get_part};
+use cli::{CountingAllocator, Part, Task, get_task, profile};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Subcommand)]
+enum Mode {
+    /// Counts the positions visited by every knot of a rope of any length.
+    Trails {
+        #[arg(short, long, default_value_t = 10)]
+        knots: usize,
+
+        /// Also lists the positions, as (row, col) with up being negative rows.
+        #[arg(short, long)]
+        positions: bool,
+    },
+}
 
 fn main() {
-    match get_part("inputs/day-9.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(input)),
+    match get_task::<Mode>("inputs/day-9.txt") {
+        Ok(Task::Part(Part::Part1(input))) => println!("{:?}", profile(|| part_1(input))),
+        Ok(Task::Part(Part::Part2(input))) => println!("{:?}", profile(|| part_2(input))),
+        Ok(Task::Mode(Mode::Trails { knots, positions }, input)) => {
+            match trails(input, knots, positions) {
+                Ok(report) => print!("{report}"),
+                Err(error) => println!("{error:?}"),
+            }
+        }
         Err(error) => println!("{error:?}"),
     }
 }
 
 fn part_1(input: String) -> Result<usize> {
-    let mut coords: Vec<Coord> = vec![(0, 0); 2];
-
-    let mut trail: HashSet<Coord> = HashSet::new();
-    trail.insert(coords[coords.len() - 1]);
-
-    input.lines().try_for_each(|line| {
-        perform_move(line, &mut coords, &mut trail)?;
-
-        Ok::<_, anyhow::Error>(())
-    })?;
-
-    Ok(trail.len())
+    Ok(simulate(&input, 2)?.tail_trail().len())
 }
 
 fn part_2(input: String) -> Result<usize> {
-    let mut coords: Vec<Coord> = vec![(0, 0); 10];
+    Ok(simulate(&input, 10)?.tail_trail().len())
+}
 
-    let mut trail: HashSet<Coord> = HashSet::new();
-    trail.insert(coords[coords.len() - 1]);
+type Coord = (isize, isize);
 
-    input.lines().try_for_each(|line| {
-        perform_move(line, &mut coords, &mut trail)?;
+/// A rope of knots, each remembering every position it has been in.
+struct Rope {
+    knots: Vec<Coord>,
+    trails: Vec<HashSet<Coord>>,
+}
 
-        Ok::<_, anyhow::Error>(())
-    })?;
+impl Rope {
+    fn new(knot_count: usize) -> Result<Self> {
+        if knot_count == 0 {
+            return Err(anyhow!("Invalid knot count: 0"));
+        }
 
-    Ok(trail.len())
-}
+        Ok(Rope {
+            knots: vec![(0, 0); knot_count],
+            trails: vec![HashSet::from([(0, 0)]); knot_count],
+        })
+    }
 
-type Coord = (isize, isize);
+    fn tail_trail(&self) -> &HashSet<Coord> {
+        &self.trails[self.trails.len() - 1]
+    }
 
-fn move_up(coord: Coord) -> Coord {
-    let (row, col) = coord;
+    /// Performs a move such as "R 4" or "UL 2", one step at a time.
+    fn perform_move(&mut self, input: &str) -> Result<()> {
+        let Some((direction, steps)) = input.split_once(" ") else {
+            return Err(anyhow!("Cannot split input: {}", input));
+        };
 
-    (row - 1, col)
-}
+        let direction = parse_direction(direction)?;
+        let steps = steps.parse::<usize>()?;
 
-fn move_right(coord: Coord) -> Coord {
-    let (row, col) = coord;
+        for _ in 0..steps {
+            self.step(direction);
+        }
 
-    (row, col + 1)
-}
+        Ok(())
+    }
 
-fn move_down(coord: Coord) -> Coord {
-    let (row, col) = coord;
+    /// Moves the head one step, and pulls every other knot after it.
+    fn step(&mut self, (row_step, col_step): Coord) {
+        let (row, col) = self.knots[0];
+        self.knots[0] = (row + row_step, col + col_step);
+        self.trails[0].insert(self.knots[0]);
 
-    (row + 1, col)
+        for index in 1..self.knots.len() {
+            self.knots[index] = tug(self.knots[index - 1], self.knots[index]);
+            self.trails[index].insert(self.knots[index]);
+        }
+    }
 }
 
-fn move_left(coord: Coord) -> Coord {
-    let (row, col) = coord;
-
-    (row, col - 1)
+/// Gets the step of a head move, with up being negative rows.
+fn parse_direction(direction: &str) -> Result<Coord> {
+    Ok(match direction {
+        "U" => (-1, 0),
+        "R" => (0, 1),
+        "D" => (1, 0),
+        "L" => (0, -1),
+        "UL" => (-1, -1),
+        "UR" => (-1, 1),
+        "DL" => (1, -1),
+        "DR" => (1, 1),
+        x => return Err(anyhow!("Invalid direction: {}", x)),
+    })
 }
 
 /// Pulls follower knot towards leader knot.
 ///
-/// Returns the new [Coord] of the tail.
+/// A follower that is no longer touching the leader takes one step towards it along each axis
+/// where they differ, which covers straight, diagonal and knight-like gaps alike.
+///
+/// Returns the new [Coord] of the follower.
 fn tug(leader: Coord, follower: Coord) -> Coord {
     let (leader_row, leader_col) = leader;
-    let (mut follower_row, mut follower_col) = follower;
-
-    // ne
-    if follower_row == leader_row + 2 && follower_col == leader_col - 2 {
-        follower_row -= 1;
-        follower_col += 1;
-    }
+    let (follower_row, follower_col) = follower;
 
-    // se
-    if follower_row == leader_row - 2 && follower_col == leader_col - 2 {
-        follower_row += 1;
-        follower_col += 1;
+    let (row_gap, col_gap) = (leader_row - follower_row, leader_col - follower_col);
+    if row_gap.abs() <= 1 && col_gap.abs() <= 1 {
+        return follower;
     }
 
-    // sw
-    if follower_row == leader_row - 2 && follower_col == leader_col + 2 {
-        follower_row += 1;
-        follower_col -= 1;
-    }
-
-    // nw
-    if follower_row == leader_row + 2 && follower_col == leader_col + 2 {
-        follower_row -= 1;
-        follower_col -= 1;
-    }
-
-    // n
-    if follower_row == leader_row + 2 {
-        follower_row -= 1;
-        follower_col = leader_col;
-    }
-
-    // e
-    if follower_col == leader_col - 2 {
-        follower_col += 1;
-        follower_row = leader_row;
-    }
+    (
+        follower_row + row_gap.signum(),
+        follower_col + col_gap.signum(),
+    )
+}
 
-    // s
-    if follower_row == leader_row - 2 {
-        follower_row += 1;
-        follower_col = leader_col;
-    }
+fn simulate(input: &str, knot_count: usize) -> Result<Rope> {
+    let mut rope = Rope::new(knot_count)?;
 
-    // w
-    if follower_col == leader_col + 2 {
-        follower_col -= 1;
-        follower_row = leader_row;
+    for line in input.lines() {
+        rope.perform_move(line)?;
     }
 
-    (follower_row, follower_col)
+    Ok(rope)
 }
 
-fn perform_move(input: &str, rope: &mut [Coord], trail: &mut HashSet<Coord>) -> Result<()> {
-    assert!(rope.len() >= 2);
-
-    let Some((direction, steps)) = input.split_once(" ") else {
-        return Err(anyhow!("Cannot split input: {}", input));
-    };
-
-    let steps = steps.parse::<usize>()?;
-
-    (0..steps).try_for_each(|_| {
-        match direction {
-            "U" => rope[0] = move_up(rope[0]),
-            "R" => rope[0] = move_right(rope[0]),
-            "D" => rope[0] = move_down(rope[0]),
-            "L" => rope[0] = move_left(rope[0]),
-            x => return Err(anyhow!("Invalid direction: {}", x)),
+fn trails(input: String, knot_count: usize, positions: bool) -> Result<String> {
+    let rope = simulate(&input, knot_count)?;
+    let mut report = String::new();
+
+    for (index, trail) in rope.trails.iter().enumerate() {
+        let name = match index {
+            0 => String::from("head"),
+            index if index == knot_count - 1 => String::from("tail"),
+            index => format!("knot {index}"),
+        };
+        writeln!(report, "{name}: {} positions", trail.len())?;
+
+        if positions {
+            let mut trail = trail.iter().collect::<Vec<_>>();
+            trail.sort();
+
+            let trail = trail
+                .iter()
+                .map(|(row, col)| format!("({row}, {col})"))
+                .collect::<Vec<_>>();
+            writeln!(report, "  {}", trail.join(" "))?;
         }
+    }
 
-        (1..rope.len()).for_each(|index| {
-            rope[index] = tug(rope[index - 1], rope[index]);
-        });
-
-        trail.insert(rope[rope.len() - 1]);
-
-        Ok(())
-    })?;
-
-    Ok(())
+    Ok(report)
 }
 
 #[cfg(test)]
@@ -202,4 +215,32 @@ U 20
 
         Ok(())
     }
+
+    #[test]
+    fn every_trail() -> Result<()> {
+        let rope = simulate(EXAMPLE_INPUT.trim(), 10)?;
+
+        assert_eq!(
+            rope.trails.iter().map(HashSet::len).collect::<Vec<_>>(),
+            [21, 13, 7, 4, 3, 2, 1, 1, 1, 1]
+        );
+        assert_eq!(simulate(EXAMPLE_INPUT.trim(), 1)?.tail_trail().len(), 21);
+        assert!(simulate(EXAMPLE_INPUT.trim(), 0).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn diagonal_moves() -> Result<()> {
+        let rope = simulate("UR 3\nDL 1\nDR 2", 2)?;
+
+        assert_eq!(rope.knots, [(0, 4), (-1, 3)]);
+        assert_eq!(
+            *rope.tail_trail(),
+            HashSet::from([(0, 0), (-1, 1), (-2, 2), (-1, 3)])
+        );
+        assert!(simulate("UU 1", 2).is_err());
+
+        Ok(())
+    }
 }